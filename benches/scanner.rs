@@ -0,0 +1,77 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use loxide::{HandleLoxResultIter, Scanner};
+
+// xorshift, so that every run scans the same source
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.next() as usize % items.len()]
+    }
+}
+
+// roughly `size` bytes of valid lox made of `pieces`, separated by spaces
+// and the odd newline
+fn generate(size: usize, pieces: &[&str]) -> String {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut source = String::with_capacity(size + 64);
+    while source.len() < size {
+        source.push_str(rng.pick(pieces));
+        source.push(if rng.next().is_multiple_of(8) { '\n' } else { ' ' });
+    }
+    source
+}
+
+const MIXED: &[&str] = &[
+    "var",
+    "a",
+    "=",
+    "1",
+    ";",
+    "print",
+    "\"hello\"",
+    "+",
+    "(",
+    ")",
+    "{",
+    "}",
+    "while",
+    "<=",
+    "3.25",
+    "// comment\n",
+    "foo_bar",
+    "!=",
+    "and",
+    "/* block */",
+    "\"esc\\n\"",
+];
+const NUMBERS: &[&str] = &["1", "42", "3.25", "1_000_000", "0xff", "6.02e23"];
+const IDENTIFIERS: &[&str] = &["a", "foo", "foo_bar", "camelCase", "x1", "café"];
+
+fn bench(c: &mut Criterion, name: &str, source: &str) {
+    let tokens = Scanner::scan(source).process_silent().0.len();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(tokens as u64));
+    group.bench_function("scan", |b| {
+        b.iter(|| Scanner::scan(black_box(source)).count())
+    });
+    group.finish();
+}
+
+fn scanner(c: &mut Criterion) {
+    bench(c, "mixed_1mb", &generate(1 << 20, MIXED));
+    bench(c, "numbers", &generate(64 << 10, NUMBERS));
+    bench(c, "identifiers", &generate(64 << 10, IDENTIFIERS));
+}
+
+criterion_group!(benches, scanner);
+criterion_main!(benches);
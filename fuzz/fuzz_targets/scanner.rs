@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use loxide::Scanner;
+
+fuzz_target!(|source: &str| {
+    // every token or error takes at least a char, so more items than that
+    // means the scanner is stuck
+    let limit = source.chars().count() + 1;
+    let mut items = 0;
+    for item in Scanner::scan(source).keep_comments(true) {
+        items += 1;
+        assert!(items <= limit, "scanner doesn't terminate");
+        if let Ok(token) = item {
+            // spans have to slice the source on char boundaries
+            let _ = &source[token.span.bytes_start()..token.span.bytes_end()];
+        }
+    }
+});
@@ -1,6 +1,9 @@
-use std::marker::PhantomData;
+use std::{borrow::Cow, marker::PhantomData};
 
-use crate::{source::SourceSpan, token::TokenKind};
+use crate::{
+    source::SourceSpan,
+    token::{TokenKind, format_number, quote_string},
+};
 
 pub type Expr<'src> = AstPart<'src, ExprKind<'src>>;
 pub type Stmt<'src> = AstPart<'src, StmtKind<'src>>;
@@ -46,7 +49,7 @@ pub enum ExprKind<'src> {
         value: Box<Expr<'src>>,
     },
     Var(&'src str),
-    LitString(&'src str),
+    LitString(Cow<'src, str>),
     LitNumber(f64),
     LitBoolean(bool),
     LitNil,
@@ -69,6 +72,60 @@ pub enum StmtKind<'src> {
     },
 }
 
+// lisp-like one-liners, handy to eyeball precedence with
+impl Expr<'_> {
+    pub fn to_sexpr(&self) -> String {
+        match &self.kind {
+            ExprKind::Binary { left, op, right } | ExprKind::Logic { left, op, right } => {
+                format!("({op} {} {})", left.to_sexpr(), right.to_sexpr())
+            }
+            ExprKind::Unary { op, right } => format!("({op} {})", right.to_sexpr()),
+            ExprKind::Grouping { inner } => format!("(group {})", inner.to_sexpr()),
+            ExprKind::Assign { id, value } => format!("(= {id} {})", value.to_sexpr()),
+            ExprKind::Var(id) => id.to_string(),
+            ExprKind::LitString(s) => quote_string(s),
+            ExprKind::LitNumber(n) => format_number(*n),
+            ExprKind::LitBoolean(b) => b.to_string(),
+            ExprKind::LitNil => "nil".to_string(),
+        }
+    }
+}
+
+impl Stmt<'_> {
+    pub fn to_sexpr(&self) -> String {
+        match &self.kind {
+            StmtKind::VariableDecl { id, init: None } => format!("(var {id})"),
+            StmtKind::VariableDecl {
+                id,
+                init: Some(init),
+            } => {
+                format!("(var {id} {})", init.to_sexpr())
+            }
+            StmtKind::Block(stmts) => {
+                let stmts: Vec<_> = stmts.iter().map(|stmt| stmt.to_sexpr()).collect();
+                format!("(block {})", stmts.join(" "))
+            }
+            StmtKind::Expr(expr) | StmtKind::ExprReturn(expr) => expr.to_sexpr(),
+            StmtKind::Print(expr) => format!("(print {})", expr.to_sexpr()),
+            StmtKind::Conditional {
+                condition,
+                then,
+                or_else: None,
+            } => format!("(if {} {})", condition.to_sexpr(), then.to_sexpr()),
+            StmtKind::Conditional {
+                condition,
+                then,
+                or_else: Some(or_else),
+            } => format!(
+                "(if {} {} {})",
+                condition.to_sexpr(),
+                then.to_sexpr(),
+                or_else.to_sexpr()
+            ),
+        }
+    }
+}
+
 pub trait DisplayTree {
     fn format_tree(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result;
 }
@@ -88,11 +145,7 @@ impl std::fmt::Display for Stmt<'_> {
 impl DisplayTree for Expr<'_> {
     fn format_tree(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
         let t = "  ".repeat(indent);
-        let span = if self.span.is_char() {
-            format!("@{}", self.span.char_start())
-        } else {
-            format!("@{}..{}", self.span.char_start(), self.span.char_end())
-        };
+        let span = format!("@{}", self.span.char_range);
         match &self.kind {
             ExprKind::Binary { left, op, right } => {
                 write!(f, "Binary{span} {op}")?;
@@ -128,11 +181,11 @@ impl DisplayTree for Expr<'_> {
                 Ok(())
             }
             ExprKind::LitString(s) => {
-                write!(f, "\"{s}\"{span}")?;
+                write!(f, "{}{span}", quote_string(s))?;
                 Ok(())
             }
             ExprKind::LitNumber(n) => {
-                write!(f, "{n}{span}")?;
+                write!(f, "{}{span}", format_number(*n))?;
                 Ok(())
             }
             ExprKind::LitBoolean(b) => {
@@ -154,11 +207,7 @@ impl DisplayTree for Expr<'_> {
 impl DisplayTree for Stmt<'_> {
     fn format_tree(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
         let t = "  ".repeat(indent);
-        let span = if self.span.is_char() {
-            format!("@{}", self.span.char_start())
-        } else {
-            format!("@{}..{}", self.span.char_start(), self.span.char_end())
-        };
+        let span = format!("@{}", self.span.char_range);
         match &self.kind {
             StmtKind::VariableDecl { id, init } => {
                 write!(f, "VariableDecl{span}: {id}")?;
@@ -0,0 +1,152 @@
+use std::{collections::VecDeque, io};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    error::{LoxErrorKind, OwnedLoxError},
+    scanner::{ResumePoint, Scanner, needs_more_input},
+    token::{OwnedToken, TokenKind},
+};
+
+// tokens may bleed into the next chunk as far as the scanner peeks ahead,
+// e.g. `1.` followed by `5`, so those close to the end wait for more input
+const LOOKAHEAD: usize = 3;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+pub type AsyncScanResult = Result<OwnedToken, OwnedLoxError>;
+
+// scans source as it's read, e.g. from a socket; what's read is kept, so
+// that spans are offsets in the whole source like with `Scanner`
+pub struct AsyncScanner<R> {
+    reader: R,
+    script: String,
+    // the start of a char that a chunk cut in half
+    partial: Vec<u8>,
+    // where to scan from next
+    resume: ResumePoint,
+    // how much of `script` the last scan saw
+    scanned: usize,
+    ready: VecDeque<AsyncScanResult>,
+    is_finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncScanner<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            script: String::new(),
+            partial: Vec::new(),
+            resume: ResumePoint {
+                byte: 0,
+                char: 0,
+                line: 1,
+                column: 0,
+            },
+            scanned: 0,
+            ready: VecDeque::new(),
+            is_finished: false,
+        }
+    }
+
+    // `None` after the `Eof`, and reading errors end scanning
+    pub async fn next(&mut self) -> io::Result<Option<AsyncScanResult>> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Ok(Some(item));
+            }
+            if self.is_finished {
+                return Ok(None);
+            }
+
+            let eof = self.read_chunk().await?;
+            // a token that's still open gets scanned again from its start, so
+            // wait until there's at least as much new input as that to keep
+            // long tokens from being rescanned on every chunk
+            let pending = self.scanned - self.resume.byte;
+            if !eof && self.script.len() - self.scanned < pending {
+                continue;
+            }
+
+            self.scan_ready(eof);
+            self.is_finished = eof;
+        }
+    }
+
+    // everything up to the `Eof`
+    pub async fn collect(mut self) -> io::Result<Vec<AsyncScanResult>> {
+        let mut items = Vec::new();
+        while let Some(item) = self.next().await? {
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    // whether the reader is done
+    async fn read_chunk(&mut self) -> io::Result<bool> {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let read = self.reader.read(&mut chunk).await?;
+        if read == 0 {
+            if !self.partial.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "source ends in the middle of a char",
+                ));
+            }
+            return Ok(true);
+        }
+
+        self.partial.extend_from_slice(&chunk[..read]);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            // cut short, the rest comes with the next chunk
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+        let text = std::str::from_utf8(&self.partial[..valid]).expect("checked above");
+        self.script.push_str(text);
+        self.partial.drain(..valid);
+
+        Ok(false)
+    }
+
+    // scans what's been read so far, keeping only what more input can't change
+    fn scan_ready(&mut self, eof: bool) {
+        self.scanned = self.script.len();
+        let hold_from = self
+            .script
+            .char_indices()
+            .rev()
+            .nth(LOOKAHEAD - 1)
+            .map_or(0, |(i, _)| i);
+
+        let mut scanner = match Scanner::resume(self.script.as_str(), self.resume) {
+            Ok(scanner) => scanner,
+            Err(err) => {
+                self.ready.push_back(Err(err.to_owned()));
+                return;
+            }
+        };
+        while let Some(item) = scanner.next() {
+            let end = scanner.resume_point().byte;
+            let complete = eof
+                || match &item {
+                    Ok(token) => token.kind != TokenKind::Eof && end <= hold_from,
+                    Err(err) => {
+                        !needs_more_input(std::slice::from_ref(err))
+                            && !matches!(err.kind, LoxErrorKind::LimitExceeded)
+                            && end <= hold_from
+                    }
+                };
+            if !complete {
+                break;
+            }
+
+            self.ready.push_back(
+                item.map(|token| token.to_owned())
+                    .map_err(|err| err.to_owned()),
+            );
+            self.resume = scanner.resume_point();
+        }
+    }
+}
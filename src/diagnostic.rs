@@ -0,0 +1,116 @@
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode};
+
+use crate::{
+    error::{LoxError, LoxResult},
+    source::{Source, SourceSpan},
+};
+
+// miette wants a byte offset and length, which may not go past the end of source
+fn byte_span(span: &SourceSpan, script: &str) -> miette::SourceSpan {
+    let start = span.bytes_start().min(script.len());
+    let end = span.bytes_end().clamp(start, script.len());
+    (start, end - start).into()
+}
+
+impl Diagnostic for LoxError<'_> {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.kind.code()))
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source.script)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let span = byte_span(&self.span, self.source.script);
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some(self.kind.to_string()),
+            span,
+        ))))
+    }
+}
+
+// `miette::Report` must be `'static`, so errors are detached from the source
+#[derive(Debug)]
+struct ReportedError {
+    message: String,
+    label: String,
+    code: &'static str,
+    span: miette::SourceSpan,
+}
+
+impl std::error::Error for ReportedError {}
+
+impl std::fmt::Display for ReportedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Diagnostic for ReportedError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.code))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some(self.label.clone()),
+            self.span,
+        ))))
+    }
+}
+
+#[derive(Debug)]
+struct LoxReport {
+    source: NamedSource<String>,
+    errors: Vec<ReportedError>,
+}
+
+impl std::error::Error for LoxReport {}
+
+impl std::fmt::Display for LoxReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Errors: {}", self.errors.len())
+    }
+}
+
+impl Diagnostic for LoxReport {
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source)
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(
+            self.errors.iter().map(|err| err as &dyn Diagnostic),
+        ))
+    }
+}
+
+pub fn report<'src, T, I>(results: I, source: &Source<'src>) -> Option<miette::Report>
+where
+    I: IntoIterator<Item = LoxResult<'src, T>>,
+{
+    let errors: Vec<_> = results
+        .into_iter()
+        .filter_map(Result::err)
+        .map(|err| ReportedError {
+            message: err.to_string(),
+            label: err.kind.to_string(),
+            code: err.kind.code(),
+            span: byte_span(&err.span, source.script),
+        })
+        .collect();
+
+    if errors.is_empty() {
+        return None;
+    }
+
+    let name = source
+        .location
+        .map(|location| location.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Some(miette::Report::new(LoxReport {
+        source: NamedSource::new(name, source.script.to_string()),
+        errors,
+    }))
+}
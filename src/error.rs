@@ -1,9 +1,9 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, ffi::OsStr};
 
 use yansi::Paint;
 
 use crate::{
-    source::{IntoSource, Source, SourceSpan},
+    source::{IntoSource, LineIndex, Source, SourceSpan},
     token::TokenKind,
 };
 
@@ -19,14 +19,57 @@ pub enum LoxErrorKind<'src> {
     ExpectedExpr,
     ExpectedValues(&'static [&'static str]),
     UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape { c: char },
+    InvalidUnicodeEscape { value: &'src str },
     InvalidNumber(String),
+    InvalidNumberSuffix { suffix: &'src str },
     InvalidConversion(&'static str, &'static str),
     InvalidAssignmentTarget,
+    InvalidOffset { offset: usize },
+    InvalidKeyword { word: &'src str, kind: &'static str },
+    IdentifierTooLong { len: usize, max: usize },
+    StringTooLong { len: usize, max: usize },
+    LimitExceeded,
     Unreachable,
 }
 
+impl LoxErrorKind<'_> {
+    // for tools to match on; codes are never reused, so new kinds take the
+    // next free one
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnexpectedCharacter(_) => "E0001",
+            Self::UnexpectedEof => "E0002",
+            Self::UnexpectedToken(_) => "E0003",
+            Self::UndefinedVariable(_) => "E0004",
+            Self::Expected(_) => "E0005",
+            Self::ExpectedExpr => "E0006",
+            Self::ExpectedValues(_) => "E0007",
+            Self::UnterminatedString => "E0008",
+            Self::UnterminatedComment => "E0009",
+            Self::InvalidEscape { .. } => "E0010",
+            Self::InvalidUnicodeEscape { .. } => "E0011",
+            Self::InvalidNumber(_) => "E0012",
+            Self::InvalidNumberSuffix { .. } => "E0013",
+            Self::InvalidConversion(_, _) => "E0014",
+            Self::InvalidAssignmentTarget => "E0015",
+            Self::InvalidOffset { .. } => "E0016",
+            Self::InvalidKeyword { .. } => "E0017",
+            Self::Unreachable => "E0018",
+            Self::IdentifierTooLong { .. } => "E0019",
+            Self::StringTooLong { .. } => "E0020",
+            Self::LimitExceeded => "E0021",
+        }
+    }
+}
+
+// `{:#}` puts the code in front, e.g. `[E0001] Unexpected character '@'`
 impl<'src> std::fmt::Display for LoxErrorKind<'src> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "[{}] ", self.code())?;
+        }
         match self {
             Self::UnexpectedCharacter(c) => write!(f, "Unexpected character '{c}'"),
             Self::UnexpectedEof => f.write_str("Unexpected end of file"),
@@ -37,12 +80,42 @@ impl<'src> std::fmt::Display for LoxErrorKind<'src> {
             Self::ExpectedValues(vs) => {
                 write!(f, "Expected values of types: {}", vs.join(", "))
             }
-            Self::UnterminatedString { .. } => write!(f, "Unterminated string"),
+            Self::UnterminatedString { .. } => write!(f, "Unterminated string, started here"),
+            Self::UnterminatedComment => f.write_str("Unterminated block comment"),
+            Self::InvalidEscape { c } => write!(f, "Invalid escape sequence '\\{c}'"),
+            Self::InvalidUnicodeEscape { value } => {
+                write!(f, "Invalid unicode escape '\\u{{{value}}}'")
+            }
             Self::InvalidNumber(s) => write!(f, "Invalid number: '{s}'"),
+            Self::InvalidNumberSuffix { suffix } => {
+                write!(f, "Invalid suffix '{suffix}' after a number")
+            }
             Self::InvalidConversion(from, to) => {
                 write!(f, "Cannot convert {from} to {to}")
             }
             Self::InvalidAssignmentTarget => f.write_str("Invalid assignment target"),
+            Self::InvalidOffset { offset } => {
+                write!(f, "Offset {offset} is not on a char boundary of the source")
+            }
+            Self::InvalidKeyword { word, kind } => {
+                write!(
+                    f,
+                    "Keyword '{word}' can't stand for a token with a value ({kind})"
+                )
+            }
+            Self::IdentifierTooLong { len, max } => {
+                write!(
+                    f,
+                    "Identifier is {len} characters long, over the limit of {max}"
+                )
+            }
+            Self::StringTooLong { len, max } => {
+                write!(
+                    f,
+                    "String is {len} characters long, over the limit of {max}"
+                )
+            }
+            Self::LimitExceeded => f.write_str("Scanning budget exceeded"),
             Self::Unreachable => {
                 f.write_str("Unreachable state reached, this is a bug. Damn.. good job")
             }
@@ -57,6 +130,23 @@ pub struct LoxError<'src> {
     pub span: SourceSpan,
 }
 
+// flat, for editors: the code, the message and where it is, with the span
+// in chars like the column
+#[cfg(feature = "serde")]
+impl serde::Serialize for LoxError<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut error = serializer.serialize_struct("LoxError", 5)?;
+        error.serialize_field("code", self.kind.code())?;
+        error.serialize_field("message", &self.kind.to_string())?;
+        error.serialize_field("line", &self.span.line)?;
+        error.serialize_field("column", &self.span.column)?;
+        error.serialize_field("span", &self.span.char_range)?;
+        error.end()
+    }
+}
+
 impl<'src> LoxError<'src> {
     pub fn new(kind: LoxErrorKind<'src>, source: impl IntoSource<'src>, span: SourceSpan) -> Self {
         Self {
@@ -69,35 +159,291 @@ impl<'src> LoxError<'src> {
 
 impl std::error::Error for LoxError<'_> {}
 
-impl std::fmt::Display for LoxError<'_> {
+// an error that doesn't borrow the source, with its message already made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedLoxError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: SourceSpan,
+}
+
+impl LoxError<'_> {
+    pub fn to_owned(&self) -> OwnedLoxError {
+        OwnedLoxError {
+            code: self.kind.code(),
+            message: self.kind.to_string(),
+            span: self.span.clone(),
+        }
+    }
+}
+
+impl std::error::Error for OwnedLoxError {}
+
+impl std::fmt::Display for OwnedLoxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let Self {
-            kind,
-            source: Source { location, .. },
-            span,
-        } = self;
+        write_location(f, &Source::default(), &self.span)?;
+        f.write_str(&self.message)
+    }
+}
+
+// what the entry points that also touch the filesystem fail with, so that
+// callers can tell a bad file from a bad script
+#[derive(Debug)]
+pub enum Error<'src> {
+    Io(std::io::Error),
+    Lox(LoxError<'src>),
+}
 
-        f.write_str("[")?;
-        if let Some(location) = location {
-            f.write_str(location.to_string_lossy().as_ref())?;
-            f.write_str(" ")?;
+impl std::error::Error for Error<'_> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            // `LoxError` borrows the script, so it can't be a `'static` source
+            Self::Lox(_) => None,
         }
-        span.line.fmt(f)?;
-        f.write_str(":")?;
-        if span.is_char() {
-            span.char_start().fmt(f)?;
+    }
+}
+
+impl std::fmt::Display for Error<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("Could not read the source"),
+            Self::Lox(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error<'_> {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl<'src> From<LoxError<'src>> for Error<'src> {
+    fn from(err: LoxError<'src>) -> Self {
+        Self::Lox(err)
+    }
+}
+
+impl std::fmt::Display for LoxError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_location(f, &self.source, &self.span)?;
+        self.kind.fmt(f)
+    }
+}
+
+// `[line:column]`, with a 1-based line and a 0-based column in chars
+fn write_location(
+    f: &mut std::fmt::Formatter<'_>,
+    Source { location, .. }: &Source<'_>,
+    span: &SourceSpan,
+) -> std::fmt::Result {
+    f.write_str("[")?;
+    if let Some(location) = location {
+        let location = location.to_string_lossy();
+        // same separators on every platform
+        if cfg!(windows) {
+            f.write_str(&location.replace('\\', "/"))?;
         } else {
-            f.write_fmt(format_args!("{}..{}", span.char_start(), span.char_end()))?;
+            f.write_str(&location)?;
+        }
+        f.write_str(" ")?;
+    }
+    write!(f, "{}:", span.line)?;
+    if span.is_char() {
+        write!(f, "{}", span.column)?;
+    } else {
+        write!(f, "{}..{}", span.column, span.column + span.char_len() - 1)?;
+    }
+    f.write_str("] ")
+}
+
+// things worth flagging that still scan fine
+#[derive(Debug)]
+pub enum LoxWarningKind<'src> {
+    LeadingZero { number: &'src str },
+    LongIdentifier { len: usize },
+}
+
+impl std::fmt::Display for LoxWarningKind<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LeadingZero { number } => write!(f, "Redundant leading zero in '{number}'"),
+            Self::LongIdentifier { len } => {
+                write!(f, "Identifier is {len} characters long")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoxWarning<'src> {
+    pub kind: LoxWarningKind<'src>,
+    pub source: Source<'src>,
+    pub span: SourceSpan,
+}
+
+impl<'src> LoxWarning<'src> {
+    pub fn new(
+        kind: LoxWarningKind<'src>,
+        source: impl IntoSource<'src>,
+        span: SourceSpan,
+    ) -> Self {
+        Self {
+            kind,
+            source: source.into_source(),
+            span,
         }
-        f.write_str("] ")?;
-        kind.fmt(f)?;
+    }
+}
 
-        Ok(())
+impl std::fmt::Display for LoxWarning<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_location(f, &self.source, &self.span)?;
+        self.kind.fmt(f)
     }
 }
 
+pub fn render_warning(warning: &LoxWarning<'_>) -> String {
+    format!("{} {}", "!".yellow().bold(), warning.to_string().yellow())
+}
+
+// reports warnings to stderr, returning how many there were
+pub fn handle_warnings<'src>(warnings: impl IntoIterator<Item = LoxWarning<'src>>) -> usize {
+    warnings
+        .into_iter()
+        .inspect(|warning| eprintln!("{}", render_warning(warning)))
+        .count()
+}
+
+// colors are off with `--no-color` (`color` is false), if NO_COLOR is set
+// to anything (see no-color.org) or if output isn't a terminal
+pub fn colors_enabled(color: bool, no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+    color && is_terminal && no_color.is_none_or(OsStr::is_empty)
+}
+
 pub trait HandleLoxResult<T>: Sized {
     fn report_err(self) -> Option<T>;
+    fn report_err_with(self, lines: &LineIndex<'_>, options: &RenderOptions) -> Option<T>;
+}
+
+// terminals usually stop tabs at multiples of 8
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+// how diagnostics are drawn, e.g. filled in from command line flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub tab_width: usize,
+    // lines shown around the offending one
+    pub context_lines: usize,
+    // like `[E0001]` in front of the message
+    pub error_codes: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: DEFAULT_TAB_WIDTH,
+            context_lines: 0,
+            error_codes: false,
+        }
+    }
+}
+
+// the offending line with a caret under the span, colored unless yansi is
+// disabled
+pub fn render_diagnostic(err: &LoxError<'_>) -> String {
+    let lines = LineIndex::new(err.source.script);
+    render_diagnostic_with(err, &lines, &RenderOptions::default())
+}
+
+// `lines` is built once for all the errors in a source, as errors don't
+// keep one; tabs are expanded to spaces, so the caret lines up however wide
+// the terminal draws them, and with any context the lines get a gutter with
+// their numbers
+pub fn render_diagnostic_with(
+    err: &LoxError<'_>,
+    lines: &LineIndex<'_>,
+    options: &RenderOptions,
+) -> String {
+    if !std::ptr::eq(lines.script(), err.source.script) {
+        return render_diagnostic_with(err, &LineIndex::new(err.source.script), options);
+    }
+
+    let RenderOptions {
+        tab_width,
+        context_lines,
+        error_codes,
+    } = *options;
+    let message = if error_codes {
+        format!("{err:#}")
+    } else {
+        err.to_string()
+    };
+    let first = err.span.line.saturating_sub(context_lines).max(1);
+    let last = (err.span.line + context_lines).min(lines.line_count());
+    let width = last.max(err.span.line).to_string().len();
+    let gutter = |line: Option<usize>| match (context_lines, line) {
+        (0, _) => String::new(),
+        (_, Some(line)) => format!("{line:>width$} │ ").dim().to_string(),
+        (_, None) => format!("{:width$} │ ", "").dim().to_string(),
+    };
+    let context = |range: std::ops::Range<usize>| {
+        range
+            .map(|line| {
+                format!(
+                    "{} {}{}\n",
+                    "░".red().bold(),
+                    gutter(Some(line)),
+                    expand_tabs(lines.line_text(line), tab_width).italic(),
+                )
+            })
+            .collect::<String>()
+    };
+
+    let line_start = lines.line_start(err.span.line);
+    let before = err
+        .source
+        .script
+        .get(line_start..err.span.bytes_start())
+        .unwrap_or_default();
+    let before = expand_tabs(before, tab_width);
+    #[cfg(feature = "graphemes")]
+    let column =
+        unicode_segmentation::UnicodeSegmentation::graphemes(before.as_str(), true).count();
+    #[cfg(not(feature = "graphemes"))]
+    let column = before.chars().count();
+    format!(
+        "{}{} {}{}\n{} {}{}{}\n{}{} {}",
+        context(first..err.span.line),
+        "▓".red().bold(),
+        gutter(Some(err.span.line)),
+        expand_tabs(lines.line_text(err.span.line), tab_width).italic(),
+        "░".red().bold(),
+        gutter(None),
+        " ".repeat(column),
+        "~".repeat(err.span.char_len().max(1)).italic().yellow(),
+        context(err.span.line + 1..last + 1),
+        "░".red().bold(),
+        message.red().bold(),
+    )
+}
+
+fn expand_tabs(text: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut expanded = String::with_capacity(text.len());
+    let mut width = 0;
+    for c in text.chars() {
+        if c == '\t' {
+            let spaces = tab_width - width % tab_width;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            width += spaces;
+        } else {
+            expanded.push(c);
+            width += 1;
+        }
+    }
+    expanded
 }
 
 impl<'src, T> HandleLoxResult<T> for LoxResult<'src, T> {
@@ -105,44 +451,57 @@ impl<'src, T> HandleLoxResult<T> for LoxResult<'src, T> {
         match self {
             Ok(value) => Some(value),
             Err(err) => {
-                println!(
-                    "{} {}\n{} {}{}\n{} {}",
-                    "▓".red().bold(),
-                    err.source
-                        .script
-                        .lines()
-                        .nth(err.span.line)
-                        .unwrap_or("<error>")
-                        .italic(),
-                    "░".red().bold(),
-                    " ".repeat(err.span.char_start()),
-                    "~".repeat(err.span.char_len() + 1).italic().yellow(),
-                    "░".red().bold(),
-                    err.to_string().red().bold(),
-                );
+                eprintln!("{}", render_diagnostic(&err));
                 None
             }
         }
     }
+
+    fn report_err_with(self, lines: &LineIndex<'_>, options: &RenderOptions) -> Option<T> {
+        match self {
+            Ok(value) => Some(value),
+            Err(err) => {
+                eprintln!("{}", render_diagnostic_with(&err, lines, options));
+                None
+            }
+        }
+    }
+}
+
+// so that a file full of garbage doesn't flood the terminal
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+// sums up the errors that went unreported
+pub fn report_more_errors(n: usize) {
+    match n {
+        0 => {}
+        1 => eprintln!("{}", "1 more error".red()),
+        n => eprintln!("{}", format!("{n} more errors").red()),
+    }
 }
 
-pub trait HandleLoxResultIter<T>: Sized {
+pub trait HandleLoxResultIter<'src, T>: Sized {
     fn report_err(self) -> impl Iterator<Item = T>;
     fn ignore_err(self) -> impl Iterator<Item = T>;
     fn process_silent(self) -> (Vec<T>, usize);
     fn process(self) -> (Vec<T>, usize);
+    fn process_capped(self, max_errors: usize) -> (Vec<T>, usize);
+    fn report_capped(
+        self,
+        max_errors: usize,
+        lines: Option<&LineIndex<'_>>,
+        options: &RenderOptions,
+    ) -> (Vec<T>, usize);
+    fn collect_errors(self) -> (Vec<T>, Vec<LoxError<'src>>);
 }
 
-impl<'src, T, I: Iterator<Item = LoxResult<'src, T>>> HandleLoxResultIter<T> for I {
+impl<'src, T, I: Iterator<Item = LoxResult<'src, T>>> HandleLoxResultIter<'src, T> for I {
     fn report_err(self) -> impl Iterator<Item = T> {
         self.filter_map(HandleLoxResult::report_err)
     }
 
     fn ignore_err(self) -> impl Iterator<Item = T> {
-        self.filter_map(|r| match r {
-            Ok(value) => Some(value),
-            Err(_err) => None,
-        })
+        self.filter_map(|r| r.ok())
     }
 
     fn process_silent(self) -> (Vec<T>, usize) {
@@ -159,12 +518,56 @@ impl<'src, T, I: Iterator<Item = LoxResult<'src, T>>> HandleLoxResultIter<T> for
     }
 
     fn process(self) -> (Vec<T>, usize) {
+        self.process_capped(DEFAULT_MAX_ERRORS)
+    }
+
+    // reports only the first `max_errors`, then sums up the rest
+    fn process_capped(self, max_errors: usize) -> (Vec<T>, usize) {
+        let (v, errors) = self.report_capped(max_errors, None, &RenderOptions::default());
+        report_more_errors(errors.saturating_sub(max_errors));
+        (v, errors)
+    }
+
+    // like `process_capped` without the sum, to share the cap between stages
+    // without `lines`, an index is built for the first error's source
+    fn report_capped(
+        self,
+        max_errors: usize,
+        lines: Option<&LineIndex<'_>>,
+        options: &RenderOptions,
+    ) -> (Vec<T>, usize) {
         let mut errors = 0_usize;
+        let mut own_lines = None;
 
         let v = self
-            .filter_map(|r| {
-                errors += if r.is_err() { 1 } else { 0 };
-                HandleLoxResult::report_err(r)
+            .filter_map(|r| match r {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    errors += 1;
+                    if errors <= max_errors {
+                        let lines = lines.unwrap_or_else(|| {
+                            own_lines.get_or_insert_with(|| LineIndex::new(err.source.script))
+                        });
+                        eprintln!("{}", render_diagnostic_with(&err, lines, options));
+                    }
+                    None
+                }
+            })
+            .collect();
+
+        (v, errors)
+    }
+
+    fn collect_errors(self) -> (Vec<T>, Vec<LoxError<'src>>) {
+        let mut errors = vec![];
+
+        let v = self
+            .filter_map(|r| match r {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    errors.push(err);
+                    None
+                }
             })
             .collect();
 
@@ -5,13 +5,13 @@ use crate::{
     environment::Environment,
     error::{LoxError, LoxErrorKind, LoxResult},
     source::{IntoSource, Source, SourceSpan},
-    token::TokenKind,
+    token::{TokenKind, format_number},
 };
 
-const NUMBER_KIND: &'static str = "number";
-const STRING_KIND: &'static str = "string";
-const BOOLEAN_KIND: &'static str = "boolean";
-const NIL_KIND: &'static str = "nil";
+const NUMBER_KIND: &str = "number";
+const STRING_KIND: &str = "string";
+const BOOLEAN_KIND: &str = "boolean";
+const NIL_KIND: &str = "nil";
 
 #[derive(Debug, Default, Clone)]
 pub enum LoxValue<'src> {
@@ -70,8 +70,7 @@ impl PartialEq for LoxValue<'_> {
 impl std::fmt::Display for LoxValue<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Number(n) if n.fract() >= f64::EPSILON => write!(f, "{n}"),
-            Self::Number(n) => write!(f, "{n:.0}"),
+            Self::Number(n) => f.write_str(&format_number(*n)),
             Self::String(s) => write!(f, "\"{s}\""),
             Self::Boolean(b) => write!(f, "{b}"),
             Self::Nil => write!(f, "nil"),
@@ -133,7 +132,7 @@ impl<'env, 'src> Interpreter<'env, 'src> {
             StmtKind::VariableDecl { id, init } => {
                 let value = init
                     .as_ref()
-                    .map(|init| self.eval(&init))
+                    .map(|init| self.eval(init))
                     .transpose()?
                     .unwrap_or_default();
 
@@ -153,19 +152,19 @@ impl<'env, 'src> Interpreter<'env, 'src> {
                 let value = self.eval(expr)?;
                 println!("{value}");
             }
-            StmtKind::ExprReturn(expr) => return self.eval(&expr),
+            StmtKind::ExprReturn(expr) => return self.eval(expr),
             StmtKind::Conditional {
                 condition,
                 then,
                 or_else,
             } => {
-                let condition_value = self.eval(&condition)?;
+                let condition_value = self.eval(condition)?;
                 let condition = self.cast_boolean(&condition_value, condition.span.clone())?;
                 if condition {
-                    let then_value = self.execute(&then)?;
+                    let then_value = self.execute(then)?;
                     return Ok(then_value);
                 } else if let Some(or_else) = or_else {
-                    let or_else_value = self.execute(&or_else)?;
+                    let or_else_value = self.execute(or_else)?;
                     return Ok(or_else_value);
                 }
             }
@@ -176,8 +175,8 @@ impl<'env, 'src> Interpreter<'env, 'src> {
     fn eval(&mut self, expr: &Expr<'src>) -> LoxResult<'src, LoxValue<'src>> {
         match &expr.kind {
             ExprKind::Binary { left, op, right } => {
-                let left_value = self.eval(&left)?;
-                let right_value = self.eval(&right)?;
+                let left_value = self.eval(left)?;
+                let right_value = self.eval(right)?;
 
                 match op {
                     TokenKind::Greater => {
@@ -241,7 +240,7 @@ impl<'env, 'src> Interpreter<'env, 'src> {
             }
 
             ExprKind::Logic { left, op, right } => {
-                let left_value = self.eval(&left)?;
+                let left_value = self.eval(left)?;
                 let left = self.cast_boolean(&left_value, left.span.clone())?;
 
                 match op {
@@ -252,7 +251,7 @@ impl<'env, 'src> Interpreter<'env, 'src> {
                         return Ok(left_value);
                     }
                     TokenKind::Or | TokenKind::And => {
-                        return self.eval(&right);
+                        return self.eval(right);
                     }
                     _ => {
                         return Err(LoxError {
@@ -264,7 +263,7 @@ impl<'env, 'src> Interpreter<'env, 'src> {
                 }
             }
             ExprKind::Unary { op, right } => {
-                let right_value = self.eval(&right)?;
+                let right_value = self.eval(right)?;
 
                 match op {
                     TokenKind::Minus => {
@@ -279,7 +278,7 @@ impl<'env, 'src> Interpreter<'env, 'src> {
                 }
             }
             ExprKind::Grouping { inner } => {
-                return self.eval(&inner);
+                return self.eval(inner);
             }
             ExprKind::Assign { id, value } => {
                 let value = self.eval(value)?;
@@ -293,7 +292,7 @@ impl<'env, 'src> Interpreter<'env, 'src> {
                 return Ok(value);
             }
             ExprKind::Var(id) => return self.get_var(id, expr.span.clone()),
-            &ExprKind::LitString(s) => return Ok(LoxValue::String(s.into())),
+            ExprKind::LitString(s) => return Ok(LoxValue::String(s.clone())),
             &ExprKind::LitNumber(n) => return Ok(LoxValue::Number(n)),
             &ExprKind::LitBoolean(b) => return Ok(LoxValue::Boolean(b)),
             ExprKind::LitNil => return Ok(LoxValue::Nil),
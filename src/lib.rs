@@ -1,8 +1,20 @@
+// `LoxError` carries its source along, so it's big by design
+#![allow(clippy::result_large_err)]
+
 pub mod ast;
+#[cfg(feature = "tokio")]
+pub mod async_scanner;
+#[cfg(feature = "miette")]
+pub mod diagnostic;
 pub mod environment;
 pub mod error;
 pub mod interpreter;
 pub mod parser;
+pub mod repl;
 pub mod scanner;
 pub mod source;
 pub mod token;
+
+pub use error::{Error, HandleLoxResultIter, LoxError};
+pub use scanner::{Scanner, scan_str};
+pub use token::{Token, TokenKind};
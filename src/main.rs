@@ -1,18 +1,26 @@
 #![allow(unused)]
 
 use eyre::Result;
-use std::{io::Write, path::Path};
+use rustyline::error::ReadlineError;
+use std::{
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+};
 use yansi::Paint;
 
 use loxide::{
     ast::{Expr, ExprKind, Stmt},
     environment::Environment,
-    error::{HandleLoxResult, HandleLoxResultIter},
+    error::{
+        DEFAULT_MAX_ERRORS, HandleLoxResult, HandleLoxResultIter, LoxError, LoxResult,
+        RenderOptions, colors_enabled, handle_warnings, report_more_errors,
+    },
     interpreter::{Interpreter, LoxValue},
     parser::Parser,
-    scanner::Scanner,
-    source::Source,
-    token::Token,
+    repl::{MetaCommand, meta_command},
+    scanner::{Scanner, needs_more_input, print_tokens_streaming},
+    source::{LineIndex, Source, SourceFile},
+    token::{Token, count_kinds, group_by_line, write_token},
 };
 
 fn main() -> Result<()> {
@@ -31,15 +39,59 @@ fn main() -> Result<()> {
             Long("print-tokens") => {
                 app.options.print_tokens = true;
             }
+            Long("case-insensitive-keywords") => {
+                app.options.case_insensitive_keywords = true;
+            }
+            Long("stream") => {
+                app.options.stream = true;
+            }
+            Long("count-tokens") => {
+                app.options.count_tokens = true;
+            }
             Long("print-ast") => {
                 app.options.print_ast = true;
             }
+            Long("print-sexpr") => {
+                app.options.print_sexpr = true;
+            }
             Long("print-nil-result") => {
                 app.options.print_nil_result = true;
             }
             Long("plain") => {
                 app.options.plain = true;
-                yansi::disable();
+                app.options.color = false;
+            }
+            Long("no-color") => {
+                app.options.color = false;
+            }
+            Long("diagnostics") => {
+                app.options.diagnostics = match parser.value()?.string()?.as_str() {
+                    "pretty" => DiagnosticsFormat::Pretty,
+                    // rejected up front rather than on the first error
+                    "json" if cfg!(feature = "serde") => DiagnosticsFormat::Json,
+                    _ => print_usage(&bin_name),
+                };
+            }
+            Long("output") => {
+                app.options.output = match parser.value()?.string()?.as_str() {
+                    "pretty" => OutputFormat::Pretty,
+                    "json" => OutputFormat::Json,
+                    "grouped" => OutputFormat::Grouped,
+                    _ => print_usage(&bin_name),
+                };
+                app.options.print_tokens = true;
+            }
+            Long("context-lines") => {
+                app.options.render.context_lines = parser.value()?.parse()?;
+            }
+            Long("error-codes") => {
+                app.options.render.error_codes = true;
+            }
+            Long("max-errors") => {
+                app.options.max_errors = parser.value()?.parse()?;
+            }
+            Short('e') | Long("eval") if app.eval.is_none() => {
+                app.eval = Some(parser.value()?.string()?);
             }
             Value(f) if app.file.is_none() => {
                 app.file = Some(f.string()?);
@@ -47,42 +99,96 @@ fn main() -> Result<()> {
             Short('v') | Long("version") => {
                 println!("{bin_name} v{version}");
             }
-            _ => {
-                println!("Usage: {bin_name} OPTIONS [file]");
-                println!("");
-                println!("OPTIONS:");
-                println!("    --print-tokens         Output scanned tokens to stdout");
-                println!("    --print-ast            Output parsed AST to stdout");
-                println!("    --print-nil-result     Print nil values from returns");
-                println!("    --plain                Disable colors and icons for output");
-                std::process::exit(64);
-            }
+            _ => print_usage(&bin_name),
         }
     }
+
+    if app.eval.is_some() && app.file.is_some() {
+        print_usage(&bin_name);
+    }
+    // streamed tokens are only ever pretty, and errors go out as they come
+    if app.options.stream
+        && (app.options.output != OutputFormat::Pretty
+            || app.options.diagnostics != DiagnosticsFormat::Pretty)
+    {
+        print_usage(&bin_name);
+    }
+
+    if !colors_enabled(
+        app.options.color,
+        std::env::var_os("NO_COLOR").as_deref(),
+        std::io::stdout().is_terminal(),
+    ) {
+        yansi::disable();
+    }
+
     app.run()
 }
 
+fn print_usage(bin_name: &str) -> ! {
+    println!("Usage: {bin_name} OPTIONS [file | -]");
+    println!();
+    println!("OPTIONS:");
+    println!("    -e, --eval <script>    Run the given script instead of a file");
+    println!("    --print-tokens         Output scanned tokens to stdout");
+    println!("    --output <format>      Output scanned tokens as `pretty`, `grouped` or `json`");
+    println!("    --stream               Output tokens as they are scanned, then stop;");
+    println!("                           only with the `pretty` output and diagnostics");
+    println!("    --count-tokens         Output how many tokens of each kind there are, then stop");
+    println!("    --case-insensitive-keywords");
+    println!("                           Recognize keywords in any casing, like `PRINT`");
+    println!("    --print-ast            Output parsed AST to stdout");
+    println!("    --print-sexpr          Output parsed AST as S-expressions to stdout");
+    println!("    --print-nil-result     Print nil values from returns");
+    println!("    --max-errors <n>       Report at most n errors in total (default: 20)");
+    println!("    --context-lines <n>    Show n lines around each error (default: 0)");
+    println!("    --diagnostics <format> Output errors as `pretty` or `json`, to stderr");
+    println!("                           (`json` needs the `serde` feature)");
+    println!("    --error-codes          Show a code like E0001 with each error");
+    println!("    --plain                Disable colors and icons for output");
+    println!("    --no-color             Disable colors for output");
+    std::process::exit(64);
+}
+
 #[derive(Debug, Default)]
 struct App {
     options: RunnerOptions,
     file: Option<String>,
+    eval: Option<String>,
 }
 
 impl App {
     pub fn run(self) -> Result<()> {
-        if let Some(file) = &self.file {
-            if !self.options.plain {
+        if let Some(script) = &self.eval {
+            let mut env = Environment::default();
+            return run_script(script, None, &mut env, &self.options);
+        }
+
+        // piped input is a whole script rather than REPL lines
+        let file = match self.file.as_deref() {
+            None if !std::io::stdin().is_terminal() => Some(STDIN_FILE),
+            file => file,
+        };
+
+        if let Some(file) = file {
+            if !self.options.plain && self.options.output != OutputFormat::Json {
                 println!(
                     "• {} running {}\n",
                     "loxide".yellow(),
-                    file.blue().underline()
+                    if file == STDIN_FILE { "<stdin>" } else { file }
+                        .blue()
+                        .underline()
                 );
             }
 
-            let file = file.as_ref();
-            let script = std::fs::read_to_string(file)?;
+            let file = read_source(file)?;
             let mut env = Environment::default();
-            return run_script(&script, Some(file), &mut env, &self.options);
+            return run_script(
+                &file.script,
+                file.location.as_deref(),
+                &mut env,
+                &self.options,
+            );
         } else {
             if !self.options.plain {
                 println!(
@@ -92,103 +198,252 @@ impl App {
                 );
             }
 
+            let mut editor = rustyline::DefaultEditor::new()?;
+            let history = history_path();
+            if let Some(history) = &history {
+                // there's no history yet on the first run
+                let _ = editor.load_history(history);
+            }
+
             let mut sources = elsa::FrozenVec::new();
             let mut env = Environment::default();
             loop {
-                print!("> ");
-                std::io::stdout().flush()?;
+                let mut buffer = match editor.readline("> ") {
+                    Ok(line) => line,
+                    Err(ReadlineError::Interrupted) => continue,
+                    Err(ReadlineError::Eof) => break,
+                    Err(err) => return Err(err.into()),
+                };
+
+                match meta_command(&buffer) {
+                    Some(MetaCommand::Quit) => break,
+                    Some(MetaCommand::Unknown(name)) => {
+                        println!("Unknown command: {name}");
+                        continue;
+                    }
+                    None => {}
+                }
 
-                let mut buffer = String::new();
-                let n = std::io::stdin().read_line(&mut buffer)?;
-                if n == 0 {
-                    break;
+                // keep reading while a string or a comment is left open,
+                // until it's closed or an empty line is submitted
+                while needs_more_input(&Scanner::scan(buffer.as_str()).collect_errors().1) {
+                    match editor.readline("... ") {
+                        Ok(line) if !line.trim().is_empty() => {
+                            buffer.push('\n');
+                            buffer.push_str(&line);
+                        }
+                        Ok(_) | Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+                        Err(err) => return Err(err.into()),
+                    }
                 }
+                editor.add_history_entry(buffer.as_str())?;
                 sources.push(buffer);
-                // trim ending newline if any
-                let trimmed = sources.last().unwrap().trim_end_matches("\n");
 
-                let _ = run_script(trimmed, None, &mut env, &self.options)?;
+                run_script(sources.last().unwrap(), None, &mut env, &self.options)?;
+            }
+
+            if let Some(history) = &history {
+                if let Some(dir) = history.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                editor.save_history(history)?;
             }
         }
         Ok(())
     }
 }
 
+// $XDG_DATA_HOME/loxide/history, or ~/.local/share/loxide/history
+fn history_path() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join(env!("CARGO_PKG_NAME")).join("history"))
+}
+
+const STDIN_FILE: &str = "-";
+
+fn read_source(file: &str) -> Result<SourceFile, loxide::error::Error<'static>> {
+    if file == STDIN_FILE {
+        let script = std::io::read_to_string(std::io::stdin())?;
+        Ok(SourceFile {
+            script,
+            location: None,
+        })
+    } else {
+        SourceFile::read(file)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+enum OutputFormat {
+    #[default]
+    Pretty,
+    // pretty, under a header for each source line
+    Grouped,
+    // needs the `serde` feature
+    Json,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+enum DiagnosticsFormat {
+    #[default]
+    Pretty,
+    // needs the `serde` feature, and reports every error
+    Json,
+}
+
 #[derive(Debug)]
 pub struct RunnerOptions {
     print_tokens: bool,
+    output: OutputFormat,
+    diagnostics: DiagnosticsFormat,
+    count_tokens: bool,
+    stream: bool,
+    case_insensitive_keywords: bool,
     print_ast: bool,
+    print_sexpr: bool,
     print_nil_result: bool,
     plain: bool,
+    // still subject to NO_COLOR and terminal detection
+    color: bool,
+    max_errors: usize,
+    render: RenderOptions,
 }
 
 impl Default for RunnerOptions {
     fn default() -> Self {
         Self {
             print_tokens: false,
+            output: OutputFormat::Pretty,
+            diagnostics: DiagnosticsFormat::Pretty,
+            count_tokens: false,
+            stream: false,
+            case_insensitive_keywords: false,
             print_ast: false,
+            print_sexpr: false,
             print_nil_result: false,
             plain: false,
+            color: true,
+            max_errors: DEFAULT_MAX_ERRORS,
+            render: RenderOptions::default(),
         }
     }
 }
 
-fn run_script<'env, 'src>(
+fn run_script<'src>(
     script: &'src str,
     location: Option<&'src Path>,
-    env: &'env mut Environment<'src>,
+    env: &mut Environment<'src>,
     options: &RunnerOptions,
 ) -> Result<()> {
-    let source = Source {
-        script: script.as_ref(),
-        location,
-    };
+    let source = Source { script, location };
+    if options.stream {
+        print_tokens_header();
+        let mut scanner =
+            Scanner::scan(&source).case_insensitive_keywords(options.case_insensitive_keywords);
+        let errors = print_tokens_streaming(
+            &mut scanner,
+            &mut std::io::stdout().lock(),
+            options.max_errors,
+            &options.render,
+        )?;
+        handle_warnings(scanner.take_warnings());
+        report_more_errors(errors.saturating_sub(options.max_errors));
+        if errors > 0 {
+            print_parsing_errors(errors, options);
+        }
+        return Ok(());
+    }
+
+    // one index for every error in the script
+    let lines = LineIndex::new(script);
+    // errors are only gathered here to be output all at once
+    let mut diagnostics = (options.diagnostics == DiagnosticsFormat::Json).then(Vec::new);
 
-    let (tokens, scanner_errors) = Scanner::scan(&source).process();
+    let mut scanner =
+        Scanner::scan(&source).case_insensitive_keywords(options.case_insensitive_keywords);
+    let (tokens, scanner_errors) = process_errors(
+        scanner.by_ref(),
+        options.max_errors,
+        &lines,
+        &options.render,
+        &mut diagnostics,
+    );
+    // stderr is all JSON then
+    if diagnostics.is_none() {
+        handle_warnings(scanner.take_warnings());
+    }
     if options.print_tokens {
-        print_tokens(&tokens);
+        match options.output {
+            OutputFormat::Pretty => print_tokens(&tokens)?,
+            OutputFormat::Grouped => print_tokens_grouped(&tokens, source.script)?,
+            OutputFormat::Json => {
+                // stdout is all JSON then
+                print_tokens_json(&tokens)?;
+                if let Some(diagnostics) = &diagnostics {
+                    print_diagnostics_json(diagnostics)?;
+                }
+                return Ok(());
+            }
+        }
+    }
+    if options.count_tokens {
+        if diagnostics.is_none() {
+            report_more_errors(scanner_errors.saturating_sub(options.max_errors));
+        }
+        print_token_counts(&tokens);
+        if let Some(diagnostics) = &diagnostics {
+            print_diagnostics_json(diagnostics)?;
+        }
+        return Ok(());
     }
 
-    let (ast, parser_errors) = Parser::parse(tokens, &source).process();
+    let (ast, parser_errors) = process_errors(
+        Parser::parse(tokens, &source),
+        options.max_errors.saturating_sub(scanner_errors),
+        &lines,
+        &options.render,
+        &mut diagnostics,
+    );
     if options.print_ast {
         print_ast(&ast);
     }
+    if options.print_sexpr {
+        print_sexpr(&ast);
+    }
 
     let total_errors = scanner_errors + parser_errors;
+    if diagnostics.is_none() {
+        report_more_errors(total_errors.saturating_sub(options.max_errors));
+    }
     if total_errors > 0 {
-        if options.plain {
-            println!("Parsing errors: {}", total_errors);
+        if let Some(diagnostics) = &diagnostics {
+            print_diagnostics_json(diagnostics)?;
         } else {
-            println!(
-                "\n{}  Parsing errors: {}",
-                "🮮".bright_red(),
-                total_errors.to_string().bright_white(),
-            );
+            print_parsing_errors(total_errors, options);
         }
         return Ok(());
     }
 
-    match Interpreter::execute_many(ast, source, env).report_err() {
-        Some(value @ LoxValue::Nil) if options.print_nil_result => {
-            if options.plain {
-                println!("{}", value);
-            } else {
-                println!("{} {}", "•".green().dim(), value.to_string().green());
-            }
+    let result = Interpreter::execute_many(ast, source, env);
+    // an empty array too, so that there's always JSON to read
+    if let Some(diagnostics) = &mut diagnostics {
+        match result {
+            Ok(value) => print_value(&value, options),
+            Err(err) => diagnostics.push(err),
         }
-        Some(LoxValue::Nil) => {}
-        Some(value) => {
-            if options.plain {
-                println!("{}", value);
-            } else {
-                println!("{} {}", "•".green().dim(), value.to_string().green());
-            }
-        }
-        _ => {
+        return print_diagnostics_json(diagnostics);
+    }
+
+    match result.report_err_with(&lines, &options.render) {
+        Some(value) => print_value(&value, options),
+        None => {
             if options.plain {
-                println!("Runtime errors: {}", 1);
+                eprintln!("Runtime errors: {}", 1);
             } else {
-                println!("\n{}  Runtime errors: {}", "🮮".dim(), 1.to_string());
+                eprintln!("\n{}  Runtime errors: {}", "🮮".dim(), 1);
             }
         }
     }
@@ -196,23 +451,98 @@ fn run_script<'env, 'src>(
     Ok(())
 }
 
-fn print_tokens<'src, 'i, I>(tokens: I)
+fn print_value(value: &LoxValue<'_>, options: &RunnerOptions) {
+    match value {
+        LoxValue::Nil if !options.print_nil_result => {}
+        value if options.plain => println!("{}", value),
+        value => println!("{} {}", "•".green().dim(), value.to_string().green()),
+    }
+}
+
+fn print_parsing_errors(count: usize, options: &RunnerOptions) {
+    if options.plain {
+        eprintln!("Parsing errors: {}", count);
+    } else {
+        eprintln!(
+            "\n{}  Parsing errors: {}",
+            "🮮".bright_red(),
+            count.to_string().bright_white(),
+        );
+    }
+}
+
+fn print_tokens<'src, 'i, I>(tokens: I) -> Result<()>
 where
     I: IntoIterator<Item = &'i Token<'src>>,
     'src: 'i,
 {
+    print_tokens_header();
+    let mut stdout = std::io::stdout().lock();
+    for (i, token) in tokens.into_iter().enumerate() {
+        write_token(&mut stdout, i, token)?;
+    }
+    Ok(())
+}
+
+fn print_tokens_header() {
     println!(
         "{} {:^5} {}",
         "─".repeat(3).magenta(),
         "Tokens".magenta(),
         "─".repeat(3).magenta()
     );
-    for (i, token) in tokens.into_iter().enumerate() {
-        println!(
-            "{}: {}",
-            format!("{i:02}").dim(),
-            token.to_string().italic()
-        );
+}
+
+fn print_tokens_grouped(tokens: &[Token<'_>], script: &str) -> Result<()> {
+    let lines = LineIndex::new(script);
+    print_tokens_header();
+    let mut stdout = std::io::stdout().lock();
+    let mut i = 0;
+    for (line, group) in group_by_line(tokens) {
+        writeln!(
+            stdout,
+            "{} {}",
+            format!("line {line}:").blue(),
+            lines.line_text(line).italic()
+        )?;
+        for token in group {
+            write!(stdout, "  ")?;
+            write_token(&mut stdout, i, token)?;
+            i += 1;
+        }
+    }
+    Ok(())
+}
+
+fn print_token_counts<'src, 'i, I>(tokens: I)
+where
+    I: IntoIterator<Item = &'i Token<'src>>,
+    'src: 'i,
+{
+    println!(
+        "{} {:^5} {}",
+        "─".repeat(3).magenta(),
+        "Token counts".magenta(),
+        "─".repeat(3).magenta()
+    );
+    for (kind, count) in count_kinds(tokens) {
+        println!("{}: {}", format!("{count:5}").dim(), kind.italic());
+    }
+}
+
+fn print_sexpr<'src, 'i, I>(ast: I)
+where
+    I: IntoIterator<Item = &'i Stmt<'src>>,
+    'src: 'i,
+{
+    println!(
+        "{} {:^5} {}",
+        "─".repeat(3).cyan(),
+        "AST".cyan(),
+        "─".repeat(3).cyan()
+    );
+    for (i, stmt) in ast.into_iter().enumerate() {
+        println!("{}: {}", format!("{i:02}").dim(), stmt.to_sexpr().italic());
     }
 }
 
@@ -231,3 +561,45 @@ where
         println!("{}: {}", format!("{i:02}").dim(), stmt.to_string().italic());
     }
 }
+
+#[cfg(feature = "serde")]
+fn print_tokens_json(tokens: &[Token<'_>]) -> Result<()> {
+    println!("{}", serde_json::to_string(tokens)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_tokens_json(_tokens: &[Token<'_>]) -> Result<()> {
+    eyre::bail!("JSON output needs loxide built with the `serde` feature")
+}
+
+// reported as they come, or gathered into `diagnostics` if there are any;
+// the sum of unreported errors is up to the caller
+fn process_errors<'src, T>(
+    results: impl Iterator<Item = LoxResult<'src, T>>,
+    max_errors: usize,
+    lines: &LineIndex<'_>,
+    render: &RenderOptions,
+    diagnostics: &mut Option<Vec<LoxError<'src>>>,
+) -> (Vec<T>, usize) {
+    match diagnostics {
+        Some(diagnostics) => {
+            let (values, errors) = results.collect_errors();
+            let count = errors.len();
+            diagnostics.extend(errors);
+            (values, count)
+        }
+        None => results.report_capped(max_errors, Some(lines), render),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn print_diagnostics_json(errors: &[LoxError<'_>]) -> Result<()> {
+    eprintln!("{}", serde_json::to_string(errors)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_diagnostics_json(_errors: &[LoxError<'_>]) -> Result<()> {
+    unreachable!("`--diagnostics json` is rejected without the `serde` feature")
+}
@@ -27,19 +27,18 @@ macro_rules! expect {
             })
         )
         .then(|| match $parser.advance() {
-            Ok(
-                $token @ Token {
-                    kind: $token_pat, ..
-                },
-            ) => {
-                $parser.stack.push($token.span.clone());
+            Ok($token) => {
+                $parser.stack.push($token.span.clone(), &$token.lexeme);
                 $ret
             }
             _ => unreachable!(),
         })
     };
     ($parser:expr, $token_pat:pat => $ret:expr) => {
-        expect!($parser, _token in $token_pat => $ret)
+        expect!($parser, token in $token_pat => match token.kind {
+            $token_pat => $ret,
+            _ => unreachable!(),
+        })
     };
     ($parser:expr, $token_pat:pat) => {
         expect!($parser, token in $token_pat => token)
@@ -100,9 +99,9 @@ where
     }
 
     pub fn decl(&mut self) -> LoxResult<'src, Stmt<'src>> {
-        self.stack.push(self.stack.get());
+        self.stack.push_current();
 
-        if let Some(_) = expect!(self, TokenKind::Var) {
+        if expect!(self, TokenKind::Var).is_some() {
             return self.var_decl();
         }
 
@@ -119,14 +118,14 @@ where
 
         consume!(self, TokenKind::Semicolon, "';' after variable declaration")?;
 
-        return Ok(Stmt::new(
+        Ok(Stmt::new(
             StmtKind::VariableDecl { id, init },
             self.stack.pop(),
-        ));
+        ))
     }
 
     pub fn stmt(&mut self) -> LoxResult<'src, Stmt<'src>> {
-        if let Some(_) = expect!(self, TokenKind::If) {
+        if expect!(self, TokenKind::If).is_some() {
             consume!(self, TokenKind::LeftParen, "'(' after if")?;
             let condition = self.expr()?;
             consume!(self, TokenKind::RightParen, "')' after if")?;
@@ -146,13 +145,13 @@ where
             ));
         }
 
-        if let Some(_) = expect!(self, TokenKind::Print) {
+        if expect!(self, TokenKind::Print).is_some() {
             let expr = self.expr()?;
             consume!(self, TokenKind::Semicolon, "';' after print statement")?;
             return Ok(Stmt::new(StmtKind::Print(Box::new(expr)), self.stack.pop()));
         }
 
-        if let Some(_) = expect!(self, TokenKind::LeftBrace) {
+        if expect!(self, TokenKind::LeftBrace).is_some() {
             let mut stmts = vec![];
             while match self.peek() {
                 Some(Token {
@@ -181,7 +180,7 @@ where
         }
 
         consume!(self, TokenKind::Semicolon, "';' after statement")?;
-        return Ok(Stmt::new(StmtKind::Expr(Box::new(expr)), self.stack.pop()));
+        Ok(Stmt::new(StmtKind::Expr(Box::new(expr)), self.stack.pop()))
     }
 
     pub fn expr(&mut self) -> LoxResult<'src, Expr<'src>> {
@@ -275,13 +274,13 @@ where
     }
 
     fn primary(&mut self) -> LoxResult<'src, Expr<'src>> {
-        if let Some(_) = expect!(self, TokenKind::False) {
+        if expect!(self, TokenKind::False).is_some() {
             return Ok(Expr::new(ExprKind::LitBoolean(false), self.stack.pop()));
         }
-        if let Some(_) = expect!(self, TokenKind::True) {
+        if expect!(self, TokenKind::True).is_some() {
             return Ok(Expr::new(ExprKind::LitBoolean(true), self.stack.pop()));
         }
-        if let Some(_) = expect!(self, TokenKind::Nil) {
+        if expect!(self, TokenKind::Nil).is_some() {
             return Ok(Expr::new(ExprKind::LitNil, self.stack.pop()));
         }
 
@@ -291,11 +290,14 @@ where
         if let Some(n) = expect!(self, TokenKind::Number(n) => n) {
             return Ok(Expr::new(ExprKind::LitNumber(n), self.stack.pop()));
         }
+        if let Some(n) = expect!(self, TokenKind::Integer(n) => n as f64) {
+            return Ok(Expr::new(ExprKind::LitNumber(n), self.stack.pop()));
+        }
         if let Some(id) = expect!(self, TokenKind::Identifier(id) => id) {
             return Ok(Expr::new(ExprKind::Var(id), self.stack.pop()));
         }
 
-        if let Some(_) = expect!(self, TokenKind::LeftParen) {
+        if expect!(self, TokenKind::LeftParen).is_some() {
             let inner = self.expr()?;
             consume!(self, TokenKind::RightParen, "closing ')'")?;
             return Ok(Expr::new(
@@ -306,8 +308,8 @@ where
             ));
         }
 
-        if let Some(_) = self.peek() {
-            let Token { kind, span } = self.advance()?;
+        if self.peek().is_some() {
+            let Token { kind, span, .. } = self.advance()?;
             Err(self.error_for(LoxErrorKind::UnexpectedToken(kind), span))
         } else {
             Err(self.error_next_char(LoxErrorKind::ExpectedExpr))
@@ -323,11 +325,7 @@ where
             return Err(self.error_next_char(LoxErrorKind::UnexpectedEof));
         };
 
-        if let Some(Token { span, .. }) = self.peek().cloned() {
-            self.stack.advance_to(span.clone());
-        } else {
-            self.stack.advance_to(next.span.clone());
-        }
+        self.stack.advance_to(next.span.clone(), &next.lexeme);
 
         Ok(next)
     }
@@ -342,22 +340,20 @@ where
                 return;
             }
 
-            match self.peek() {
-                Some(Token {
-                    kind:
-                        TokenKind::Class
-                        | TokenKind::Fun
-                        | TokenKind::Var
-                        | TokenKind::For
-                        | TokenKind::If
-                        | TokenKind::While
-                        | TokenKind::Print
-                        | TokenKind::Return,
-                    ..
-                }) => {
-                    return;
-                }
-                _ => {}
+            if let Some(Token {
+                kind:
+                    TokenKind::Class
+                    | TokenKind::Fun
+                    | TokenKind::Var
+                    | TokenKind::For
+                    | TokenKind::If
+                    | TokenKind::While
+                    | TokenKind::Print
+                    | TokenKind::Return,
+                ..
+            }) = self.peek()
+            {
+                return;
             }
         }
     }
@@ -367,13 +363,7 @@ where
     }
 
     fn error_next_char(&self, kind: LoxErrorKind<'src>) -> LoxError<'src> {
-        let span = self.stack.get();
-        let span = SourceSpan {
-            line: span.line,
-            char_range: (span.char_end().saturating_add(1)..=span.char_end().saturating_add(1)),
-            bytes_range: (span.bytes_end().saturating_add(1)..=span.bytes_end().saturating_add(1)),
-        };
-        LoxError::new(kind, self.source.clone(), span)
+        LoxError::new(kind, self.source.clone(), self.stack.end())
     }
 
     fn is_end(&mut self) -> bool {
@@ -0,0 +1,14 @@
+#[derive(Debug, PartialEq, Eq)]
+pub enum MetaCommand<'a> {
+    Quit,
+    Unknown(&'a str),
+}
+
+// REPL lines starting with `:` are commands for loxide itself, not Lox
+pub fn meta_command(line: &str) -> Option<MetaCommand<'_>> {
+    let name = line.trim().strip_prefix(':')?;
+    match name {
+        "quit" | "exit" => Some(MetaCommand::Quit),
+        name => Some(MetaCommand::Unknown(name)),
+    }
+}
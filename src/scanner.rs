@@ -1,43 +1,238 @@
 use peek_again::Peekable;
-use std::str::CharIndices;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    io::{self, Write},
+    str::CharIndices,
+};
+use unicode_ident::{is_xid_continue, is_xid_start};
 
 use crate::{
-    error::{LoxError, LoxErrorKind, LoxResult},
-    source::{IntoSource, Source, SourceSpanTracker},
-    token::{Token, TokenKind},
+    error::{
+        HandleLoxResultIter, LoxError, LoxErrorKind, LoxResult, LoxWarning, LoxWarningKind,
+        RenderOptions, render_diagnostic_with,
+    },
+    source::{
+        IntoSource, LineIndex, Position, Source, SourceFile, SourceSpan, SourceSpanTracker, Span,
+    },
+    token::{Token, TokenKind, write_token},
 };
 
+const BOM: char = '\u{FEFF}';
+
+// longer identifiers get a warning
+const LONG_IDENTIFIER_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumePoint {
+    pub byte: usize,
+    pub char: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
 #[derive(Debug)]
 pub struct Scanner<'src> {
     source: Source<'src>,
     iter: Peekable<CharIndices<'src>>,
     tracker: SourceSpanTracker,
     is_terminated: bool,
+    keep_comments: bool,
+    case_insensitive_keywords: bool,
+    keywords: Option<&'src HashMap<&'src str, TokenKind<'src>>>,
+    warnings: Vec<LoxWarning<'src>>,
+    strict_ascii: bool,
+    max_identifier_len: Option<usize>,
+    max_string_len: Option<usize>,
+    budget: Option<usize>,
 }
 
 impl<'src> Scanner<'src> {
     pub fn scan(source: impl IntoSource<'src>) -> Self {
+        Self::at(source.into_source(), SourceSpanTracker::default())
+    }
+
+    // picks scanning up at a byte offset, e.g. after the last token an edit
+    // didn't touch; `line` and `column` are where that offset is
+    pub fn scan_from(
+        source: impl IntoSource<'src>,
+        byte_offset: usize,
+        line: usize,
+        column: usize,
+    ) -> LoxResult<'src, Self> {
         let source = source.into_source();
-        let iter = Peekable::new(source.script.char_indices());
+        let chars = source.script[..source.script.floor_char_boundary(byte_offset)]
+            .chars()
+            .count();
+        Self::resume(
+            source,
+            ResumePoint {
+                byte: byte_offset,
+                char: chars,
+                line,
+                column,
+            },
+        )
+    }
+
+    // like `scan_from`, without counting chars up to the offset again
+    pub fn resume(source: impl IntoSource<'src>, point: ResumePoint) -> LoxResult<'src, Self> {
+        let source = source.into_source();
+        if !source.script.is_char_boundary(point.byte) {
+            let span = SourceSpan {
+                line: point.line,
+                column: point.column,
+                char_range: Span::new(point.char, point.char + 1),
+                bytes_range: Span::new(point.byte, point.byte + 1),
+            };
+            let kind = LoxErrorKind::InvalidOffset { offset: point.byte };
+            return Err(LoxError::new(kind, source, span));
+        }
+
+        let tracker = SourceSpanTracker::at(point.line, point.column, point.char, point.byte);
+        Ok(Self::at(source, tracker))
+    }
+
+    fn at(source: Source<'src>, mut tracker: SourceSpanTracker) -> Self {
+        let start = tracker.current_byte();
+        let mut iter = Peekable::new(source.script[start..].char_indices());
+
+        // a leading BOM isn't part of the script, but offsets still count it
+        if start == 0 && source.script.starts_with(BOM) {
+            iter.next();
+            tracker.skip_char(BOM);
+            tracker.consume();
+        }
 
         Self {
             source,
             iter,
-            tracker: SourceSpanTracker::default(),
+            tracker,
             is_terminated: false,
+            keep_comments: false,
+            case_insensitive_keywords: false,
+            keywords: None,
+            warnings: Vec::new(),
+            strict_ascii: false,
+            max_identifier_len: None,
+            max_string_len: None,
+            budget: None,
         }
     }
 
+    // where the scanner got to, to pick up from there later with `resume`
+    pub fn resume_point(&self) -> ResumePoint {
+        ResumePoint {
+            byte: self.tracker.current_byte(),
+            char: self.tracker.current_char(),
+            line: self.tracker.current_line(),
+            column: self.tracker.current_column(),
+        }
+    }
+
+    // the scanner only borrows, so `SourceFile::read` does the reading
+    pub fn from_file(file: &'src SourceFile) -> Self {
+        Self::scan(file)
+    }
+
+    // emit comments as tokens instead of skipping them, e.g. for a formatter;
+    // the parser doesn't expect these
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.keep_comments = keep;
+        self
+    }
+
+    // `PRINT` and `Print` are `print` too; identifiers keep their casing
+    pub fn case_insensitive_keywords(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive_keywords = case_insensitive;
+        self
+    }
+
+    // words looked up before the built-in keywords, e.g. `func` for `fun` in
+    // a dialect; they may only stand for tokens without a value
+    pub fn keywords(
+        mut self,
+        keywords: &'src HashMap<&'src str, TokenKind<'src>>,
+    ) -> LoxResult<'src, Self> {
+        let invalid = keywords.iter().find(|(_, kind)| {
+            matches!(
+                kind,
+                TokenKind::Identifier(_)
+                    | TokenKind::String(_)
+                    | TokenKind::Integer(_)
+                    | TokenKind::Number(_)
+                    | TokenKind::LineComment(_)
+                    | TokenKind::BlockComment(_)
+                    | TokenKind::Eof
+            )
+        });
+        if let Some((&word, kind)) = invalid {
+            let kind = LoxErrorKind::InvalidKeyword {
+                word,
+                kind: kind.lexeme_kind_name(),
+            };
+            return Err(self.error(kind));
+        }
+
+        self.keywords = Some(keywords);
+        Ok(self)
+    }
+
+    // nothing but ASCII outside of strings and comments
+    pub fn strict_ascii(mut self, strict: bool) -> Self {
+        self.strict_ascii = strict;
+        self
+    }
+
+    // limits in chars, for untrusted input; no limits by default
+    pub fn max_identifier_len(mut self, max: usize) -> Self {
+        self.max_identifier_len = Some(max);
+        self
+    }
+
+    pub fn max_string_len(mut self, max: usize) -> Self {
+        self.max_string_len = Some(max);
+        self
+    }
+
+    // chars the scanner may go through, trivia included, before it gives up
+    // with an error and ends without an `Eof`
+    pub fn budget(mut self, chars: usize) -> Self {
+        self.budget = Some(chars);
+        self
+    }
+
+    // warnings found so far, which unlike errors don't stop a token
+    pub fn take_warnings(&mut self) -> Vec<LoxWarning<'src>> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    // scans everything up front with the options set so far, stopping at
+    // the first error
+    pub fn scan_all(self) -> LoxResult<'src, Vec<Token<'src>>> {
+        self.collect()
+    }
+
+    // each token along with where it starts, e.g. for editor tooling
+    pub fn tokens_with_positions(
+        self,
+    ) -> impl Iterator<Item = LoxResult<'src, (Token<'src>, Position)>> {
+        let lines = LineIndex::new(self.source.script);
+        self.map(move |token| {
+            token.map(|token| {
+                let (line, col) = lines.line_col(token.span.bytes_start());
+                (token, Position { line, col })
+            })
+        })
+    }
+
     fn next_token(&mut self) -> LoxResult<'src, Token<'src>> {
         macro_rules! token {
             ($kind:expr) => {
-                Ok(Token {
-                    kind: $kind,
-                    span: self.tracker.consume(),
-                })
+                Ok(self.token($kind))
             };
             ($char:expr => $kind:expr, else => $other:expr) => {
-                if self.find($char) {
+                if self.find($char)? {
                     token!($kind)
                 } else {
                     token!($other)
@@ -51,148 +246,500 @@ impl<'src> Scanner<'src> {
             '{' => token!(TokenKind::LeftBrace),
             '}' => token!(TokenKind::RightBrace),
             ',' => token!(TokenKind::Comma),
+            ':' => token!(TokenKind::Colon),
+            '?' => token!(TokenKind::Question),
             '.' => token!(TokenKind::Dot),
-            '-' => token!(TokenKind::Minus),
-            '+' => token!(TokenKind::Plus),
+            '-' => token!('=' => TokenKind::MinusEqual, else => TokenKind::Minus),
+            '+' => token!('=' => TokenKind::PlusEqual, else => TokenKind::Plus),
+            '%' => token!(TokenKind::Percent),
             ';' => token!(TokenKind::Semicolon),
-            '*' => token!(TokenKind::Star),
+            '*' => token!('=' => TokenKind::StarEqual, else => TokenKind::Star),
             '!' => token!('=' => TokenKind::BangEqual, else => TokenKind::Bang),
+            '=' if self.find('>')? => token!(TokenKind::FatArrow),
             '=' => token!('=' => TokenKind::EqualEqual, else => TokenKind::Equal),
             '<' => token!('=' => TokenKind::LessEqual, else => TokenKind::Less),
             '>' => token!('=' => TokenKind::GreaterEqual, else => TokenKind::Greater),
-            '/' => {
-                if self.find('/') {
-                    // Skip comments and try to scan again
-                    self.consume_until('\n')?;
-                    self.next_token()
-                } else {
-                    token!(TokenKind::Slash)
-                }
+            '/' => token!('=' => TokenKind::SlashEqual, else => TokenKind::Slash),
+            '"' if (self.peek(), self.peek_2()) == (Some('"'), Some('"')) => {
+                self.advance()?;
+                self.advance()?;
+                self.triple_quoted_string()
             }
             '"' => self.string(),
-            '0'..='9' => self.number(),
-            'a'..='z' | 'A'..='Z' => self.ident(),
-            ' ' | '\r' | '\t' | '\n' => {
-                self.tracker.consume();
-                self.next_token()
+            'r' if self.peek() == Some('"') => {
+                self.advance()?;
+                self.raw_string()
             }
+            '0'..='9' => self.number(),
+            c if (c == '_' || is_xid_start(c)) && self.allows(c) => self.ident(),
             c => {
+                let err = self.error(LoxErrorKind::UnexpectedCharacter(c));
                 self.tracker.consume();
-                Err(self.error(LoxErrorKind::UnexpectedCharacter(c)))
+                Err(err)
             }
         }
     }
 
+    // a loop rather than recursion, so long runs of blank lines or comments
+    // can't overflow the stack; comments are returned when kept
+    fn skip_trivia(&mut self) -> LoxResult<'src, Option<Token<'src>>> {
+        loop {
+            match (self.peek(), self.peek_2()) {
+                (Some(' ' | '\r' | '\t' | '\n'), _) => {
+                    self.advance()?;
+                }
+                (Some('/'), Some('/')) => {
+                    self.consume_until('\n')?;
+                    if self.keep_comments {
+                        let lexeme = self.current_span_lexeme();
+                        let text = lexeme[2..].strip_suffix('\r').unwrap_or(&lexeme[2..]);
+                        return Ok(Some(self.token(TokenKind::LineComment(text))));
+                    }
+                }
+                // `#!/usr/bin/env loxide`, only on the very first line
+                (Some('#'), Some('!')) if self.is_at_start() => {
+                    self.consume_until('\n')?;
+                }
+                (Some('/'), Some('*')) => {
+                    self.advance()?;
+                    self.advance()?;
+                    // nested ones included
+                    self.block_comment()?;
+                    if self.keep_comments {
+                        let lexeme = self.current_span_lexeme();
+                        let text = &lexeme[2..lexeme.len() - 2];
+                        return Ok(Some(self.token(TokenKind::BlockComment(text))));
+                    }
+                }
+                _ => break,
+            }
+            self.tracker.consume();
+        }
+        Ok(None)
+    }
+
+    fn is_at_start(&self) -> bool {
+        let bom = if self.source.script.starts_with(BOM) {
+            BOM.len_utf8()
+        } else {
+            0
+        };
+        self.tracker.current_byte() == bom
+    }
+
+    // `None` only when input ends between tokens; running out in the middle
+    // of one is a real `UnexpectedEof`
     fn try_next_token(&mut self) -> LoxResult<'src, Option<Token<'src>>> {
-        match self.next_token() {
-            Ok(token) => Ok(Some(token)),
-            Err(LoxError {
-                kind: LoxErrorKind::UnexpectedEof,
-                ..
-            }) => Ok(None),
-            Err(err) => Err(err),
+        if let Some(comment) = self.skip_trivia()? {
+            return Ok(Some(comment));
         }
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        self.next_token().map(Some)
     }
 
     fn string(&mut self) -> LoxResult<'src, Token<'src>> {
-        if !self.consume_until('"')? {
-            return Err(self.error(LoxErrorKind::UnterminatedString));
+        // the opening quote, since by the time the string turns out unterminated
+        // the scanner is already at EOF, possibly lines away
+        let opening = self.tracker.get();
+        let content_start = self.tracker.current_byte();
+        // only allocated once an escape sequence is met, borrowed from source otherwise
+        let mut decoded: Option<String> = None;
+        // first invalid escape, reported after the whole string is consumed
+        let mut error = None;
+
+        loop {
+            match self.peek() {
+                None => {
+                    self.tracker.consume();
+                    return Err(self.error_for(LoxErrorKind::UnterminatedString, opening));
+                }
+                Some('"') => break,
+                Some('\\') => {
+                    let (escape_char, escape_byte) =
+                        (self.tracker.current_char(), self.tracker.current_byte());
+                    let decoded = decoded.get_or_insert_with(|| {
+                        self.source.script[content_start..escape_byte].to_string()
+                    });
+                    self.advance()?;
+
+                    let Some(c) = self.peek() else {
+                        continue;
+                    };
+                    let (c_char, c_byte) =
+                        (self.tracker.current_char(), self.tracker.current_byte());
+                    self.advance()?;
+
+                    match c {
+                        'n' => decoded.push('\n'),
+                        't' => decoded.push('\t'),
+                        'r' => decoded.push('\r'),
+                        '\\' => decoded.push('\\'),
+                        '"' => decoded.push('"'),
+                        '0' => decoded.push('\0'),
+                        'u' => match self.unicode_escape(escape_char, escape_byte) {
+                            Ok(c) => decoded.push(c),
+                            Err(err) => {
+                                error.get_or_insert(err);
+                            }
+                        },
+                        c => {
+                            let span = self.tracker.get_from(c_char, c_byte);
+                            error.get_or_insert(
+                                self.error_for(LoxErrorKind::InvalidEscape { c }, span),
+                            );
+                        }
+                    }
+                }
+                Some(c) => {
+                    self.advance()?;
+                    if let Some(decoded) = &mut decoded {
+                        decoded.push(c);
+                    }
+                }
+            }
         }
 
+        let content_end = self.tracker.current_byte();
         self.advance()?;
 
-        let lexeme = self.current_span_lexeme();
-        let s = lexeme.trim_matches('"');
+        if let Some(error) = error {
+            self.tracker.consume();
+            return Err(error);
+        }
 
-        Ok(Token {
-            kind: TokenKind::String(s),
-            span: self.tracker.consume(),
-        })
+        let s = match decoded {
+            Some(decoded) => Cow::Owned(decoded),
+            None => Cow::Borrowed(&self.source.script[content_start..content_end]),
+        };
+
+        self.string_token(s)
     }
 
-    fn number(&mut self) -> LoxResult<'src, Token<'src>> {
-        while matches!(self.peek(), Some('0'..='9')) {
+    // `"""..."""` for multi-line text, taken verbatim up to the closing quotes
+    // but without the newline right after the opening ones
+    fn triple_quoted_string(&mut self) -> LoxResult<'src, Token<'src>> {
+        let opening = self.tracker.get();
+        if (self.peek(), self.peek_2()) == (Some('\r'), Some('\n')) {
+            self.advance()?;
+        }
+        if self.peek() == Some('\n') {
             self.advance()?;
         }
+        let content_start = self.tracker.current_byte();
 
-        match (self.peek(), self.peek_2()) {
-            (Some('.'), Some('0'..='9')) => {
-                self.advance()?;
-                while matches!(self.peek(), Some('0'..='9')) {
+        while !self.source.script[self.tracker.current_byte()..].starts_with(r#"""""#) {
+            if self.peek().is_none() {
+                self.tracker.consume();
+                return Err(self.error_for(LoxErrorKind::UnterminatedString, opening));
+            }
+            self.advance()?;
+        }
+
+        let content_end = self.tracker.current_byte();
+        for _ in 0..3 {
+            self.advance()?;
+        }
+
+        let s = &self.source.script[content_start..content_end];
+        self.string_token(Cow::Borrowed(s))
+    }
+
+    // `r"..."` keeps backslashes as they are, so it's always borrowed; `\"`
+    // still doesn't end it, but stays in the contents as is too
+    fn raw_string(&mut self) -> LoxResult<'src, Token<'src>> {
+        let opening = self.tracker.get();
+        let content_start = self.tracker.current_byte();
+
+        loop {
+            match self.peek() {
+                None => {
+                    self.tracker.consume();
+                    return Err(self.error_for(LoxErrorKind::UnterminatedString, opening));
+                }
+                Some('"') => break,
+                Some('\\') => {
+                    self.advance()?;
+                    if self.peek().is_some() {
+                        self.advance()?;
+                    }
+                }
+                Some(_) => {
                     self.advance()?;
                 }
             }
-            _ => {}
         }
 
+        let content_end = self.tracker.current_byte();
+        self.advance()?;
+
+        let s = &self.source.script[content_start..content_end];
+        self.string_token(Cow::Borrowed(s))
+    }
+
+    fn string_token(&mut self, s: Cow<'src, str>) -> LoxResult<'src, Token<'src>> {
+        if let Some(max) = self.max_string_len {
+            let len = s.chars().count();
+            if len > max {
+                let err = self.error(LoxErrorKind::StringTooLong { len, max });
+                self.tracker.consume();
+                return Err(err);
+            }
+        }
+
+        Ok(self.token(TokenKind::String(s)))
+    }
+
+    fn unicode_escape(&mut self, escape_char: usize, escape_byte: usize) -> LoxResult<'src, char> {
+        // "\u" is already consumed, expecting "{hex digits}"
+        let opened = self.find('{')?;
+
+        let digits_start = self.tracker.current_byte();
+        while matches!(self.peek(), Some(c) if opened && c.is_ascii_hexdigit()) {
+            self.advance()?;
+        }
+        let value = &self.source.script[digits_start..self.tracker.current_byte()];
+
+        let closed = opened && self.find('}')?;
+        let span = self.tracker.get_from(escape_char, escape_byte);
+
+        closed
+            .then(|| u32::from_str_radix(value, 16).ok())
+            .flatten()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error_for(LoxErrorKind::InvalidUnicodeEscape { value }, span))
+    }
+
+    fn block_comment(&mut self) -> LoxResult<'src, ()> {
+        // span of the outermost "/*", for the unterminated error
+        let start = self.tracker.get();
+
+        let mut depth = 1_usize;
+        while depth > 0 {
+            match (self.peek(), self.peek_2()) {
+                (Some('/'), Some('*')) => {
+                    self.advance()?;
+                    self.advance()?;
+                    depth += 1;
+                }
+                (Some('*'), Some('/')) => {
+                    self.advance()?;
+                    self.advance()?;
+                    depth -= 1;
+                }
+                (Some(_), _) => {
+                    self.advance()?;
+                }
+                (None, _) => {
+                    self.tracker.consume();
+                    return Err(self.error_for(LoxErrorKind::UnterminatedComment, start));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn number(&mut self) -> LoxResult<'src, Token<'src>> {
+        if self.current_span_lexeme() == "0" && matches!(self.peek(), Some('x' | 'X')) {
+            return self.hex_number();
+        }
+
+        self.digits()?;
+
+        let mut is_float = false;
+        if let (Some('.'), Some('0'..='9')) = (self.peek(), self.peek_2()) {
+            self.advance()?;
+            self.digits()?;
+            is_float = true;
+        }
+
+        // exponent is taken whole, even without digits, so that "1e" is
+        // reported as a malformed number rather than a number and an identifier
+        if let Some('e' | 'E') = self.peek() {
+            self.advance()?;
+            if let Some('+' | '-') = self.peek() {
+                self.advance()?;
+            }
+            self.digits()?;
+            is_float = true;
+        }
+        self.number_suffix()?;
+
         let lexeme = self.current_span_lexeme();
-        let n = lexeme
-            .parse::<f64>()
-            .or_else(|_| lexeme.parse::<u64>().map(|n| n as f64))
-            .map_err(|_err| self.error(LoxErrorKind::InvalidNumber(lexeme.to_string())))?;
+        let kind = Self::strip_separators(lexeme)
+            .and_then(|digits| {
+                // integers too big for `i64` still make a fine `f64`
+                let integer = (!is_float)
+                    .then(|| digits.parse::<i64>().ok())
+                    .flatten()
+                    .map(TokenKind::Integer);
+                // lox has no literal for infinity, so that's only ever an overflow
+                let float = || digits.parse::<f64>().ok().filter(|n| n.is_finite());
+                integer.or_else(|| float().map(TokenKind::Number))
+            })
+            .ok_or_else(|| self.error(LoxErrorKind::InvalidNumber(lexeme.to_string())));
+        let kind = match kind {
+            Ok(kind) => kind,
+            Err(err) => {
+                self.tracker.consume();
+                return Err(err);
+            }
+        };
+        if lexeme.starts_with('0') && lexeme[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            self.warn(LoxWarningKind::LeadingZero { number: lexeme });
+        }
 
-        Ok(Token {
-            kind: TokenKind::Number(n),
-            span: self.tracker.consume(),
-        })
+        Ok(self.token(kind))
+    }
+
+    // `123abc` is most likely a typo, so it's not taken as a number and an
+    // identifier
+    fn number_suffix(&mut self) -> LoxResult<'src, ()> {
+        // with `strict_ascii`, `1é` is a number and then a bad char like `é`
+        // is anywhere else
+        if !matches!(self.peek(), Some(c) if (c == '_' || is_xid_start(c)) && self.allows(c)) {
+            return Ok(());
+        }
+
+        let suffix_start = self.tracker.current_byte();
+        while matches!(self.peek(), Some(c) if is_xid_continue(c) && self.allows(c)) {
+            self.advance()?;
+        }
+        let suffix = &self.source.script[suffix_start..self.tracker.current_byte()];
+        let err = self.error(LoxErrorKind::InvalidNumberSuffix { suffix });
+        self.tracker.consume();
+        Err(err)
+    }
+
+    fn digits(&mut self) -> LoxResult<'src, ()> {
+        while matches!(self.peek(), Some('0'..='9' | '_')) {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    // remove `_` digit separators, which are only allowed between two digits
+    fn strip_separators(lexeme: &str) -> Option<Cow<'_, str>> {
+        if !lexeme.contains('_') {
+            return Some(Cow::Borrowed(lexeme));
+        }
+
+        let bytes = lexeme.as_bytes();
+        let is_digit =
+            |i: Option<usize>| i.and_then(|i| bytes.get(i)).is_some_and(u8::is_ascii_digit);
+        let misplaced = bytes
+            .iter()
+            .enumerate()
+            .any(|(i, &b)| b == b'_' && !(is_digit(i.checked_sub(1)) && is_digit(Some(i + 1))));
+
+        (!misplaced).then(|| Cow::Owned(lexeme.replace('_', "")))
+    }
+
+    fn hex_number(&mut self) -> LoxResult<'src, Token<'src>> {
+        // "0" is already consumed
+        self.advance()?;
+        while matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) {
+            self.advance()?;
+        }
+        self.number_suffix()?;
+
+        let lexeme = self.current_span_lexeme();
+        let n = u64::from_str_radix(&lexeme[2..], 16)
+            .map(|n| i64::try_from(n).map_or(TokenKind::Number(n as f64), TokenKind::Integer))
+            .map_err(|_err| self.error(LoxErrorKind::InvalidNumber(lexeme.to_string())));
+        let kind = match n {
+            Ok(kind) => kind,
+            Err(err) => {
+                self.tracker.consume();
+                return Err(err);
+            }
+        };
+
+        Ok(self.token(kind))
     }
 
     fn ident(&mut self) -> LoxResult<'src, Token<'src>> {
-        while matches!(
-            self.peek(),
-            Some('0'..='9') | Some('a'..='z') | Some('A'..='Z')
-        ) {
+        while matches!(self.peek(), Some(c) if is_xid_continue(c) && self.allows(c)) {
             self.advance()?;
         }
 
         let lexeme = self.current_span_lexeme();
-        let kind = match lexeme {
-            "and" => TokenKind::And,
-            "class" => TokenKind::Class,
-            "else" => TokenKind::Else,
-            "false" => TokenKind::False,
-            "fun" => TokenKind::Fun,
-            "for" => TokenKind::For,
-            "if" => TokenKind::If,
-            "nil" => TokenKind::Nil,
-            "or" => TokenKind::Or,
-            "print" => TokenKind::Print,
-            "return" => TokenKind::Return,
-            "super" => TokenKind::Super,
-            "this" => TokenKind::This,
-            "true" => TokenKind::True,
-            "var" => TokenKind::Var,
-            "while" => TokenKind::While,
-            lexeme => TokenKind::Identifier(lexeme),
+        let len = lexeme.chars().count();
+        if let Some(max) = self.max_identifier_len
+            && len > max
+        {
+            let err = self.error(LoxErrorKind::IdentifierTooLong { len, max });
+            self.tracker.consume();
+            return Err(err);
+        }
+        if len > LONG_IDENTIFIER_LEN {
+            self.warn(LoxWarningKind::LongIdentifier { len });
+        }
+
+        let keyword = if self.case_insensitive_keywords {
+            self.keyword(&lexeme.to_lowercase())
+        } else {
+            self.keyword(lexeme)
         };
 
-        Ok(Token {
-            kind,
-            span: self.tracker.consume(),
-        })
+        Ok(self.token(keyword.unwrap_or(TokenKind::Identifier(lexeme))))
+    }
+
+    fn keyword(&self, word: &str) -> Option<TokenKind<'src>> {
+        self.keywords
+            .and_then(|keywords| keywords.get(word).cloned())
+            .or_else(|| TokenKind::keyword(word))
     }
 
     fn error(&self, kind: LoxErrorKind<'src>) -> LoxError<'src> {
         LoxError::new(kind, self.source.clone(), self.tracker.get())
     }
 
+    fn error_for(&self, kind: LoxErrorKind<'src>, span: SourceSpan) -> LoxError<'src> {
+        LoxError::new(kind, self.source.clone(), span)
+    }
+
+    fn allows(&self, c: char) -> bool {
+        !self.strict_ascii || c.is_ascii()
+    }
+
+    fn warn(&mut self, kind: LoxWarningKind<'src>) {
+        let warning = LoxWarning::new(kind, self.source.clone(), self.tracker.get());
+        self.warnings.push(warning);
+    }
+
+    fn token(&mut self, kind: TokenKind<'src>) -> Token<'src> {
+        let lexeme = self.current_span_lexeme();
+        Token {
+            kind,
+            lexeme: lexeme.into(),
+            span: self.tracker.consume(),
+        }
+    }
+
     fn current_span_lexeme(&self) -> &'src str {
         self.source.span(&self.tracker.get())
     }
 
     fn advance(&mut self) -> LoxResult<'src, char> {
+        if let Some(budget) = &mut self.budget {
+            if *budget == 0 {
+                return Err(self.error(LoxErrorKind::LimitExceeded));
+            }
+            *budget -= 1;
+        }
+
         let (_idx, char) = self
             .iter
             .next()
             .ok_or_else(|| self.error(LoxErrorKind::UnexpectedEof))?;
 
-        match char {
-            '\n' => {
-                self.tracker.advance_line(1);
-            }
-            _ => {}
-        }
         self.tracker.advance_char(char);
+        if char == '\n' {
+            self.tracker.advance_line(1);
+        }
 
         Ok(char)
     }
@@ -205,13 +752,14 @@ impl<'src> Scanner<'src> {
         self.iter.peek_2().map(|(_, c)| *c)
     }
 
-    fn find(&mut self, expected: char) -> bool {
+    // the budget can run out on the second char of a token
+    fn find(&mut self, expected: char) -> LoxResult<'src, bool> {
         match self.peek() {
             Some(c) if c == expected => {
-                let _ = self.advance();
-                true
+                self.advance()?;
+                Ok(true)
             }
-            _ => false,
+            _ => Ok(false),
         }
     }
 
@@ -230,19 +778,185 @@ impl<'src> Scanner<'src> {
     }
 }
 
+// tokens are written out as they're scanned, so that nothing piles up for a
+// big file; the first `max_errors` errors go to stderr like with
+// `report_capped`, and the count of all of them is returned
+pub fn print_tokens_streaming(
+    scanner: &mut Scanner<'_>,
+    sink: &mut impl Write,
+    max_errors: usize,
+    render: &RenderOptions,
+) -> io::Result<usize> {
+    const FLUSH_EVERY: usize = 1024;
+
+    let mut errors = 0;
+    let mut index = 0;
+    let lines = LineIndex::new(scanner.source.script);
+    for token in scanner {
+        match token {
+            Ok(token) => {
+                write_token(sink, index, &token)?;
+                index += 1;
+                if index.is_multiple_of(FLUSH_EVERY) {
+                    sink.flush()?;
+                }
+            }
+            Err(err) => {
+                errors += 1;
+                if errors <= max_errors {
+                    eprintln!("{}", render_diagnostic_with(&err, &lines, render));
+                }
+            }
+        }
+    }
+    sink.flush()?;
+
+    Ok(errors)
+}
+
+// everything in one go, for when a script without a location is all there is
+pub fn scan_str(source: &str) -> (Vec<Token<'_>>, Vec<LoxError<'_>>) {
+    Scanner::scan(source).collect_errors()
+}
+
+// input failing only for being cut short could be finished on the next line
+pub fn needs_more_input(errors: &[LoxError<'_>]) -> bool {
+    matches!(
+        errors,
+        [LoxError {
+            kind: LoxErrorKind::UnterminatedString | LoxErrorKind::UnterminatedComment,
+            ..
+        }]
+    )
+}
+
+// `.` is only part of a number when a digit follows, so `123.` scans as a
+// number and a dot; these are the dots right after a number with no member
+// name after them, which are most likely a typo worth a warning
+pub fn dangling_dots<'a, 'src>(tokens: &'a [Token<'src>]) -> impl Iterator<Item = &'a Token<'src>> {
+    tokens.iter().enumerate().filter_map(|(i, dot)| {
+        let number = tokens.get(i.checked_sub(1)?)?;
+        let is_number = matches!(number.kind, TokenKind::Integer(_) | TokenKind::Number(_));
+        let is_member = matches!(
+            tokens.get(i + 1),
+            Some(Token {
+                kind: TokenKind::Identifier(_),
+                ..
+            })
+        );
+        let is_adjacent = number.span.char_end() == dot.span.char_start();
+        (matches!(dot.kind, TokenKind::Dot) && is_number && is_adjacent && !is_member)
+            .then_some(dot)
+    })
+}
+
 impl<'src> Iterator for Scanner<'src> {
     type Item = LoxResult<'src, Token<'src>>;
 
+    // the `Eof` token comes exactly once, as the last item, unless the budget
+    // runs out first
     fn next(&mut self) -> Option<Self::Item> {
-        match (self.try_next_token(), self.is_terminated) {
-            (Ok(None), false) => {
+        if self.is_terminated {
+            return None;
+        }
+
+        match self.try_next_token() {
+            Ok(Some(token)) => Some(Ok(token)),
+            Err(err) => {
+                self.is_terminated = matches!(err.kind, LoxErrorKind::LimitExceeded);
+                Some(Err(err))
+            }
+            Ok(None) => {
                 self.is_terminated = true;
                 Some(Ok(Token {
                     kind: TokenKind::Eof,
+                    lexeme: "".into(),
                     span: self.tracker.eof(),
                 }))
             }
-            (token, _) => token.transpose(),
         }
     }
+
+    // every token or error takes at least a char, so there can't be more of
+    // them than bytes left, plus the `Eof`
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.is_terminated {
+            return (0, Some(0));
+        }
+        let remaining = self.source.script.len() - self.tracker.current_byte();
+        (1, Some(remaining + 1))
+    }
+}
+
+// owns its script, so it can be kept around without a lifetime, e.g. by a
+// REPL holding on to its input; scanners borrow from it as needed and are
+// configured like any other
+#[derive(Debug, Default, Clone)]
+pub struct OwnedScanner {
+    file: SourceFile,
+}
+
+impl OwnedScanner {
+    pub fn new(script: impl Into<String>) -> Self {
+        Self::from_file(SourceFile {
+            script: script.into(),
+            location: None,
+        })
+    }
+
+    pub fn from_file(file: SourceFile) -> Self {
+        Self { file }
+    }
+
+    pub fn file(&self) -> &SourceFile {
+        &self.file
+    }
+
+    pub fn scan(&self) -> Scanner<'_> {
+        Scanner::from_file(&self.file)
+    }
+}
+
+impl From<String> for OwnedScanner {
+    fn from(script: String) -> Self {
+        Self::new(script)
+    }
+}
+
+// `is_terminated` keeps it at `None` after the `Eof`
+impl std::iter::FusedIterator for Scanner<'_> {}
+
+// lookahead over scanned tokens, pulling from the scanner only when needed
+#[derive(Debug)]
+pub struct TokenStream<'src> {
+    scanner: Scanner<'src>,
+    buffer: VecDeque<LoxResult<'src, Token<'src>>>,
+}
+
+impl<'src> TokenStream<'src> {
+    pub fn new(scanner: Scanner<'src>) -> Self {
+        Self {
+            scanner,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<&LoxResult<'src, Token<'src>>> {
+        self.peek_nth(0)
+    }
+
+    pub fn peek_nth(&mut self, n: usize) -> Option<&LoxResult<'src, Token<'src>>> {
+        while self.buffer.len() <= n {
+            self.buffer.push_back(self.scanner.next()?);
+        }
+        self.buffer.get(n)
+    }
+}
+
+impl<'src> Iterator for TokenStream<'src> {
+    type Item = LoxResult<'src, Token<'src>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.pop_front().or_else(|| self.scanner.next())
+    }
 }
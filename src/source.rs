@@ -1,8 +1,10 @@
 use std::{
-    ops::{Deref, RangeInclusive},
-    path::Path,
+    ops::{Deref, Range, RangeInclusive},
+    path::{Path, PathBuf},
 };
 
+use crate::error::Error;
+
 #[derive(Debug, Default, Clone)]
 pub struct Source<'src> {
     pub script: &'src str,
@@ -10,13 +12,9 @@ pub struct Source<'src> {
 }
 
 impl<'src> Source<'src> {
-    pub fn line(&self, n: usize) -> &'src str {
-        self.script.lines().nth(n).unwrap_or_default()
-    }
-
     pub fn span(&self, span: &SourceSpan) -> &'src str {
         self.script
-            .get(span.bytes_range.clone())
+            .get(Range::from(span.bytes_range))
             .unwrap_or_default()
     }
 }
@@ -29,6 +27,55 @@ impl Deref for Source<'_> {
     }
 }
 
+// the same file spelled differently (`a.lox`, `./b/../a.lox`, absolute) gets
+// the same location, relative to the working directory when it's inside;
+// a path that can't be resolved is kept as is
+pub fn normalize_location(path: &Path) -> PathBuf {
+    let Ok(path) = std::fs::canonicalize(path) else {
+        return path.to_path_buf();
+    };
+    let path = std::env::current_dir()
+        .and_then(std::fs::canonicalize)
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or(path);
+    // a backslash is a plain file name character everywhere else
+    if cfg!(windows) {
+        slash_windows_path(&path.to_string_lossy()).into()
+    } else {
+        path
+    }
+}
+
+// a windows path without the verbatim `\\?\` prefix that `canonicalize`
+// adds, with forward slashes, so that locations read the same everywhere
+pub fn slash_windows_path(path: &str) -> String {
+    let path = match path.strip_prefix(r"\\?\") {
+        Some(unc) if unc.starts_with(r"UNC\") => format!(r"\\{}", &unc[4..]),
+        Some(path) => path.to_string(),
+        None => path.to_string(),
+    };
+    path.replace('\\', "/")
+}
+
+// a script read into memory along with where it's from, for a `Source` to
+// borrow from
+#[derive(Debug, Default, Clone)]
+pub struct SourceFile {
+    pub script: String,
+    pub location: Option<PathBuf>,
+}
+
+impl SourceFile {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error<'static>> {
+        let path = path.as_ref();
+        Ok(Self {
+            script: std::fs::read_to_string(path)?,
+            location: Some(normalize_location(path)),
+        })
+    }
+}
+
 pub trait IntoSource<'src> {
     fn into_source(self) -> Source<'src>;
 }
@@ -39,16 +86,25 @@ impl<'src> IntoSource<'src> for Source<'src> {
     }
 }
 
-impl<'a, 'src> IntoSource<'src> for &'a Source<'src> {
+impl<'src> IntoSource<'src> for &Source<'src> {
     fn into_source(self) -> Source<'src> {
         self.clone()
     }
 }
 
+impl<'src> IntoSource<'src> for &'src SourceFile {
+    fn into_source(self) -> Source<'src> {
+        Source {
+            script: &self.script,
+            location: self.location.as_deref(),
+        }
+    }
+}
+
 impl<'src> IntoSource<'src> for &'src str {
     fn into_source(self) -> Source<'src> {
         Source {
-            script: self.as_ref(),
+            script: self,
             location: None,
         }
     }
@@ -64,64 +120,285 @@ impl<'src> IntoSource<'src> for (&'src str, &'src Path) {
     }
 }
 
+// 1-based line and 0-based column in chars
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+// byte offsets where each line starts, for lookups without rescanning
+#[derive(Debug, Clone)]
+pub struct LineIndex<'src> {
+    script: &'src str,
+    line_starts: Vec<usize>,
+}
+
+impl<'src> LineIndex<'src> {
+    pub fn new(script: &'src str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(script.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            script,
+            line_starts,
+        }
+    }
+
+    // 1-based line and 0-based column in chars, same as `SourceSpan`
+    pub fn line_col(&self, byte_offset: usize) -> (usize, usize) {
+        let line = self
+            .line_starts
+            .partition_point(|&start| start <= byte_offset);
+        let start = self.line_starts[line - 1];
+        let column = self
+            .script
+            .get(start..byte_offset.min(self.script.len()))
+            .map_or(0, |text| text.chars().count());
+        (line, column)
+    }
+
+    // columns as user-perceived characters, so emoji sequences count as one
+    #[cfg(feature = "graphemes")]
+    pub fn grapheme_col(&self, byte_offset: usize) -> (usize, usize) {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let (line, _) = self.line_col(byte_offset);
+        let start = self.line_starts[line - 1];
+        let column = self
+            .script
+            .get(start..byte_offset.min(self.script.len()))
+            .map_or(0, |text| text.graphemes(true).count());
+        (line, column)
+    }
+
+    // byte offset of a 1-based line, or the end of source past the last one
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts
+            .get(line.wrapping_sub(1))
+            .copied()
+            .unwrap_or(self.script.len())
+    }
+
+    // 1-based, without the line ending
+    pub fn line_text(&self, line: usize) -> &'src str {
+        let Some(&start) = self.line_starts.get(line.wrapping_sub(1)) else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.script.len());
+        let text = &self.script[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn script(&self) -> &'src str {
+        self.script
+    }
+}
+
+// offsets with an exclusive end, so that an empty span is `start == end`;
+// ordered by start, then by end
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.start..self.end).contains(&offset)
+    }
+
+    // smallest span covering both
+    pub fn merge(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+// `start` for a single char, `start..end` for anything else, empty included
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.len() == 1 {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}..{}", self.start, self.end)
+        }
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+impl From<RangeInclusive<usize>> for Span {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Self::new(*range.start(), range.end() + 1)
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourceSpan {
     pub line: usize,
-    pub char_range: RangeInclusive<usize>,
-    pub bytes_range: RangeInclusive<usize>,
+    // column of the span start within its line in chars, unlike `char_range`
+    // which is an offset in the whole source; `bytes_range` is there for
+    // slicing, and `LineIndex::grapheme_col` for columns as editors show them
+    pub column: usize,
+    pub char_range: Span,
+    pub bytes_range: Span,
+}
+
+// in source order, by the byte offsets; the rest only breaks ties to stay
+// consistent with `Eq`
+impl Ord for SourceSpan {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.bytes_range
+            .cmp(&other.bytes_range)
+            .then(self.char_range.cmp(&other.char_range))
+            .then(self.line.cmp(&other.line))
+            .then(self.column.cmp(&other.column))
+    }
+}
+
+impl PartialOrd for SourceSpan {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl SourceSpan {
     pub fn is_char(&self) -> bool {
-        self.char_len() == 0
+        self.char_len() <= 1
     }
 
     pub fn char_len(&self) -> usize {
-        self.char_start().abs_diff(self.char_end())
+        self.char_range.len()
     }
 
     pub fn char_start(&self) -> usize {
-        *self.char_range.start()
+        self.char_range.start
     }
 
+    // exclusive
     pub fn char_end(&self) -> usize {
-        *self.char_range.end()
+        self.char_range.end
     }
 
     pub fn bytes_start(&self) -> usize {
-        *self.bytes_range.start()
+        self.bytes_range.start
     }
 
+    // exclusive
     pub fn bytes_end(&self) -> usize {
-        *self.bytes_range.end()
+        self.bytes_range.end
+    }
+
+    // line and column right after the span, given the text it covers; a
+    // multi-line token like a string ends on a later line than it starts
+    pub fn end_position(&self, text: &str) -> (usize, usize) {
+        match text.rsplit_once('\n') {
+            Some((before, last)) => (
+                self.line + before.matches('\n').count() + 1,
+                last.chars().count(),
+            ),
+            None => (self.line, self.column + self.char_len()),
+        }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
 pub struct SourceSpanTracker {
+    start_line: usize,
     current_line: usize,
+    start_column: usize,
+    current_column: usize,
     start_char: usize,
     current_char: usize,
     start_byte: usize,
     current_byte: usize,
 }
 
+impl Default for SourceSpanTracker {
+    fn default() -> Self {
+        Self::at(1, 0, 0, 0)
+    }
+}
+
 impl SourceSpanTracker {
+    pub fn at(line: usize, column: usize, char: usize, byte: usize) -> Self {
+        Self {
+            start_line: line,
+            current_line: line,
+            start_column: column,
+            current_column: column,
+            start_char: char,
+            current_char: char,
+            start_byte: byte,
+            current_byte: byte,
+        }
+    }
+
+    // a span over several lines, like a multi-line string, is on the line
+    // where it starts, same as its column
     pub fn get(&self) -> SourceSpan {
+        SourceSpan {
+            line: self.start_line,
+            column: self.start_column,
+            char_range: Span::new(self.start_char, self.current_char),
+            bytes_range: Span::new(self.start_byte, self.current_byte),
+        }
+    }
+
+    // span from an earlier position on the current line up to now
+    pub fn get_from(&self, start_char: usize, start_byte: usize) -> SourceSpan {
         SourceSpan {
             line: self.current_line,
-            char_range: self.start_char..=(self.current_char.saturating_sub(1)),
-            bytes_range: self.start_byte..=(self.current_byte.saturating_sub(1)),
+            column: self
+                .current_column
+                .saturating_sub(self.current_char - start_char),
+            char_range: Span::new(start_char, self.current_char),
+            bytes_range: Span::new(start_byte, self.current_byte),
         }
     }
 
+    // empty span right after the last char, on the last line
     pub fn eof(&self) -> SourceSpan {
         let char = self.current_char;
         let byte = self.current_byte;
         SourceSpan {
             line: self.current_line,
-            char_range: char..=char,
-            bytes_range: byte..=byte,
+            column: self.current_column,
+            char_range: Span::new(char, char),
+            bytes_range: Span::new(byte, byte),
         }
     }
 
@@ -133,25 +410,48 @@ impl SourceSpanTracker {
         self.current_byte
     }
 
-    pub fn set(&mut self, span: SourceSpan) {
-        self.current_line = span.line;
+    pub fn current_line(&self) -> usize {
+        self.current_line
+    }
+
+    pub fn current_column(&self) -> usize {
+        self.current_column
+    }
+
+    // `text` is what `span` covers, to know which line it ends on
+    pub fn set(&mut self, span: SourceSpan, text: &str) {
+        let (end_line, end_column) = span.end_position(text);
+        self.start_line = span.line;
+        self.current_line = end_line;
+        self.start_column = span.column;
+        self.current_column = end_column;
         self.start_char = span.char_start();
-        self.current_char = span.char_end().saturating_add(1);
+        self.current_char = span.char_end();
         self.start_byte = span.bytes_start();
-        self.current_byte = span.bytes_end().saturating_add(1);
+        self.current_byte = span.bytes_end();
     }
 
     pub fn advance_line(&mut self, lines: usize) {
         self.current_line += lines;
+        self.current_column = 0;
     }
 
     pub fn advance_char(&mut self, char: char) {
+        self.current_column += 1;
+        self.current_char += 1;
+        self.current_byte += char.len_utf8();
+    }
+
+    // moves past a char that doesn't take up a column
+    pub fn skip_char(&mut self, char: char) {
         self.current_char += 1;
         self.current_byte += char.len_utf8();
     }
 
     pub fn consume(&mut self) -> SourceSpan {
         let span = self.get();
+        self.start_line = self.current_line;
+        self.start_column = self.current_column;
         self.start_char = self.current_char;
         self.start_byte = self.current_byte;
         span
@@ -166,11 +466,31 @@ impl SourceSpanTrackerStack {
         self.0.last().unwrap().get()
     }
 
-    pub fn push(&mut self, start: SourceSpan) {
-        let mut tracker = SourceSpanTracker::default();
-        tracker.start_char = start.char_start();
-        tracker.start_byte = start.bytes_start();
-        self.0.push(tracker);
+    // empty span right after the last token
+    pub fn end(&self) -> SourceSpan {
+        self.0.last().unwrap().eof()
+    }
+
+    // the new span starts where `start` does and so far ends where it ends;
+    // `text` is what `start` covers
+    pub fn push(&mut self, start: SourceSpan, text: &str) {
+        let (end_line, end_column) = start.end_position(text);
+        self.0.push(SourceSpanTracker {
+            start_line: start.line,
+            current_line: end_line,
+            start_column: start.column,
+            current_column: end_column,
+            start_char: start.char_start(),
+            current_char: start.char_end(),
+            start_byte: start.bytes_start(),
+            current_byte: start.bytes_end(),
+        });
+    }
+
+    // a new span over the same as the current one so far
+    pub fn push_current(&mut self) {
+        let current = self.0.last().unwrap().clone();
+        self.0.push(current);
     }
 
     pub fn pop(&mut self) -> SourceSpan {
@@ -179,11 +499,13 @@ impl SourceSpanTrackerStack {
         last.get()
     }
 
-    pub fn advance_to(&mut self, span: SourceSpan) {
+    pub fn advance_to(&mut self, span: SourceSpan, text: &str) {
+        let (end_line, end_column) = span.end_position(text);
         for tracker in &mut self.0 {
-            tracker.current_line = span.line;
-            tracker.current_char = span.char_end().saturating_add(1);
-            tracker.current_byte = span.bytes_end().saturating_add(1);
+            tracker.current_line = end_line;
+            tracker.current_column = end_column;
+            tracker.current_char = span.char_end();
+            tracker.current_byte = span.bytes_end();
         }
     }
 }
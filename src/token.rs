@@ -1,6 +1,12 @@
+use std::{borrow::Cow, io::Write, ops::Range};
+
+use yansi::{Paint, Style};
+
 use crate::source::SourceSpan;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+// `Number` holds an `f64`, so equality is by hand, see below
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenKind<'src> {
     // Single characters: brackers
     LeftParen,
@@ -9,10 +15,13 @@ pub enum TokenKind<'src> {
     RightBrace,
 
     // Single characters: other
+    Colon,
     Comma,
     Dot,
     Minus,
+    Percent,
     Plus,
+    Question,
     Semicolon,
     Slash,
     Star,
@@ -22,19 +31,32 @@ pub enum TokenKind<'src> {
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
 
     // Literals
     Identifier(&'src str),
-    String(&'src str),
+    String(Cow<'src, str>),
+    // literals with neither a fraction nor an exponent
+    Integer(i64),
     Number(f64),
 
+    // Trivia, only when the scanner keeps comments
+    LineComment(&'src str),
+    BlockComment(&'src str),
+
     // Keywords
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
@@ -53,6 +75,186 @@ pub enum TokenKind<'src> {
     Eof,
 }
 
+// by hand, so that a `NaN` number is still equal to itself; that makes it
+// reflexive, so kinds and tokens can be `Eq`
+impl PartialEq for TokenKind<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Identifier(a), Self::Identifier(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Self::LineComment(a), Self::LineComment(b)) => a == b,
+            (Self::BlockComment(a), Self::BlockComment(b)) => a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for TokenKind<'_> {}
+
+// stable lowercase names, unlike `Debug` which may change
+impl TokenKind<'_> {
+    pub fn lexeme_kind_name(&self) -> &'static str {
+        match self {
+            Self::LeftParen => "left_paren",
+            Self::RightParen => "right_paren",
+            Self::LeftBrace => "left_brace",
+            Self::RightBrace => "right_brace",
+            Self::Colon => "colon",
+            Self::Comma => "comma",
+            Self::Dot => "dot",
+            Self::Minus => "minus",
+            Self::Percent => "percent",
+            Self::Plus => "plus",
+            Self::Question => "question",
+            Self::Semicolon => "semicolon",
+            Self::Slash => "slash",
+            Self::Star => "star",
+            Self::Bang => "bang",
+            Self::BangEqual => "bang_equal",
+            Self::Equal => "equal",
+            Self::EqualEqual => "equal_equal",
+            Self::FatArrow => "fat_arrow",
+            Self::Greater => "greater",
+            Self::GreaterEqual => "greater_equal",
+            Self::Less => "less",
+            Self::LessEqual => "less_equal",
+            Self::PlusEqual => "plus_equal",
+            Self::MinusEqual => "minus_equal",
+            Self::StarEqual => "star_equal",
+            Self::SlashEqual => "slash_equal",
+            Self::Identifier(_) => "identifier",
+            Self::String(_) => "string",
+            Self::Integer(_) => "integer",
+            Self::Number(_) => "number",
+            Self::LineComment(_) => "line_comment",
+            Self::BlockComment(_) => "block_comment",
+            Self::And => "and",
+            Self::Break => "break",
+            Self::Class => "class",
+            Self::Continue => "continue",
+            Self::Else => "else",
+            Self::False => "false",
+            Self::Fun => "fun",
+            Self::For => "for",
+            Self::If => "if",
+            Self::Nil => "nil",
+            Self::Or => "or",
+            Self::Print => "print",
+            Self::Return => "return",
+            Self::Super => "super",
+            Self::This => "this",
+            Self::True => "true",
+            Self::Var => "var",
+            Self::While => "while",
+            Self::Eof => "eof",
+        }
+    }
+}
+
+impl TokenKind<'_> {
+    pub fn keyword(word: &str) -> Option<TokenKind<'static>> {
+        Some(match word {
+            "and" => TokenKind::And,
+            "break" => TokenKind::Break,
+            "class" => TokenKind::Class,
+            "continue" => TokenKind::Continue,
+            "else" => TokenKind::Else,
+            "false" => TokenKind::False,
+            "fun" => TokenKind::Fun,
+            "for" => TokenKind::For,
+            "if" => TokenKind::If,
+            "nil" => TokenKind::Nil,
+            "or" => TokenKind::Or,
+            "print" => TokenKind::Print,
+            "return" => TokenKind::Return,
+            "super" => TokenKind::Super,
+            "this" => TokenKind::This,
+            "true" => TokenKind::True,
+            "var" => TokenKind::Var,
+            "while" => TokenKind::While,
+            _ => return None,
+        })
+    }
+}
+
+// groups for highlighters and the like; a kind is in one group at most, and
+// identifiers, comments and the rest of the punctuation are in none
+impl TokenKind<'_> {
+    // keywords are named after their word, so `keyword` is the one list
+    pub fn is_keyword(&self) -> bool {
+        TokenKind::keyword(self.lexeme_kind_name()).is_some()
+    }
+
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::String(_) | TokenKind::Integer(_) | TokenKind::Number(_)
+        )
+    }
+
+    pub fn is_operator(&self) -> bool {
+        use TokenKind::*;
+
+        matches!(
+            self,
+            Colon
+                | Dot
+                | Minus
+                | Percent
+                | Plus
+                | Question
+                | Slash
+                | Star
+                | Bang
+                | BangEqual
+                | Equal
+                | EqualEqual
+                | FatArrow
+                | Greater
+                | GreaterEqual
+                | Less
+                | LessEqual
+                | PlusEqual
+                | MinusEqual
+                | StarEqual
+                | SlashEqual
+        )
+    }
+
+    pub fn is_bracket(&self) -> bool {
+        use TokenKind::*;
+
+        matches!(self, LeftParen | RightParen | LeftBrace | RightBrace)
+    }
+}
+
+impl Token<'_> {
+    pub fn is_keyword(&self) -> bool {
+        self.kind.is_keyword()
+    }
+
+    pub fn is_literal(&self) -> bool {
+        self.kind.is_literal()
+    }
+
+    pub fn is_operator(&self) -> bool {
+        self.kind.is_operator()
+    }
+
+    pub fn is_bracket(&self) -> bool {
+        self.kind.is_bracket()
+    }
+}
+
+// a keyword, or else an identifier borrowing the word
+impl<'src> From<&'src str> for TokenKind<'src> {
+    fn from(word: &'src str) -> Self {
+        TokenKind::keyword(word).unwrap_or(TokenKind::Identifier(word))
+    }
+}
+
 impl std::fmt::Display for TokenKind<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -60,10 +262,13 @@ impl std::fmt::Display for TokenKind<'_> {
             Self::RightParen => write!(f, ")"),
             Self::LeftBrace => write!(f, "{{"),
             Self::RightBrace => write!(f, "}}"),
+            Self::Colon => write!(f, ":"),
             Self::Comma => write!(f, ","),
             Self::Dot => write!(f, "."),
             Self::Minus => write!(f, "-"),
+            Self::Percent => write!(f, "%"),
             Self::Plus => write!(f, "+"),
+            Self::Question => write!(f, "?"),
             Self::Semicolon => write!(f, ";"),
             Self::Slash => write!(f, "/"),
             Self::Star => write!(f, "*"),
@@ -71,15 +276,26 @@ impl std::fmt::Display for TokenKind<'_> {
             Self::BangEqual => write!(f, "!="),
             Self::Equal => write!(f, "="),
             Self::EqualEqual => write!(f, "=="),
+            Self::FatArrow => write!(f, "=>"),
             Self::Greater => write!(f, ">"),
             Self::GreaterEqual => write!(f, ">="),
             Self::Less => write!(f, "<"),
             Self::LessEqual => write!(f, "<="),
+            Self::PlusEqual => write!(f, "+="),
+            Self::MinusEqual => write!(f, "-="),
+            Self::StarEqual => write!(f, "*="),
+            Self::SlashEqual => write!(f, "/="),
             Self::Identifier(s) => write!(f, "{s}"),
             Self::String(s) => write!(f, "{s}"),
-            Self::Number(n) => write!(f, "{n}"),
+            Self::Integer(n) => write!(f, "{n}"),
+            // always with a fraction or an exponent, to tell it from `Integer`
+            Self::Number(n) => write!(f, "{n:?}"),
+            Self::LineComment(s) => write!(f, "//{s}"),
+            Self::BlockComment(s) => write!(f, "/*{s}*/"),
             Self::And => write!(f, "and"),
+            Self::Break => write!(f, "break"),
             Self::Class => write!(f, "class"),
+            Self::Continue => write!(f, "continue"),
             Self::Else => write!(f, "else"),
             Self::False => write!(f, "false"),
             Self::Fun => write!(f, "fun"),
@@ -99,26 +315,299 @@ impl std::fmt::Display for TokenKind<'_> {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+// how numbers are shown to users: integral values without a fraction, and
+// the rest in the shortest form that parses back to the same `f64`, which is
+// what `Display` for floats does already
+pub fn format_number(n: f64) -> String {
+    n.to_string()
+}
+
+// a string in quotes with its escapes back, so that it scans into the same
+// string again
+pub fn quote_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\0' => quoted.push_str("\\0"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// a style per group of kinds, to tell tokens apart when printing them
+pub fn color_for(kind: &TokenKind<'_>) -> Style {
+    let style = Style::new().italic();
+    if kind.is_keyword() {
+        style.blue()
+    } else if kind.is_literal() {
+        style.green()
+    } else if kind.is_operator() || kind.is_bracket() {
+        style.yellow()
+    } else {
+        match kind {
+            TokenKind::Identifier(_) => style.cyan(),
+            TokenKind::LineComment(_) | TokenKind::BlockComment(_) | TokenKind::Eof => style.dim(),
+            // the rest of the punctuation, like `;`
+            _ => style.yellow(),
+        }
+    }
+}
+
+// a line of `--print-tokens`
+pub fn write_token(sink: &mut impl Write, index: usize, token: &Token<'_>) -> std::io::Result<()> {
+    writeln!(
+        sink,
+        "{}: {}",
+        format!("{index:02}").dim(),
+        token.to_string().paint(color_for(&token.kind))
+    )
+}
+
+// tokens bucketed by the line they start on, in source order
+pub fn group_by_line<'a, 'src: 'a>(
+    tokens: impl IntoIterator<Item = &'a Token<'src>>,
+) -> Vec<(usize, Vec<&'a Token<'src>>)> {
+    let mut groups = Vec::<(usize, Vec<_>)>::new();
+    for token in tokens {
+        match groups.last_mut() {
+            Some((line, group)) if *line == token.span.line => group.push(token),
+            _ => groups.push((token.span.line, vec![token])),
+        }
+    }
+    groups
+}
+
+// how many tokens there are of each kind, most common first, not counting
+// the `Eof`
+pub fn count_kinds<'a, 'src: 'a>(
+    tokens: impl IntoIterator<Item = &'a Token<'src>>,
+) -> Vec<(&'static str, usize)> {
+    let mut counts = hashbrown::HashMap::<&'static str, usize>::new();
+    for token in tokens {
+        if !matches!(token.kind, TokenKind::Eof) {
+            *counts.entry(token.kind.lexeme_kind_name()).or_default() += 1;
+        }
+    }
+
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
+// source text that scans back into the same kinds, one space apart; strings
+// get their escapes back and line comments end their line
+pub fn render_source<'a, 'src: 'a>(kinds: impl IntoIterator<Item = &'a TokenKind<'src>>) -> String {
+    let mut source = String::new();
+    for kind in kinds {
+        match kind {
+            TokenKind::String(s) => {
+                source.push_str(&quote_string(s));
+                source.push(' ');
+            }
+            TokenKind::LineComment(_) => {
+                source.push_str(&kind.to_string());
+                source.push('\n');
+            }
+            TokenKind::Eof => {}
+            kind => {
+                source.push_str(&kind.to_string());
+                source.push(' ');
+            }
+        }
+    }
+    source
+}
+
+// deserializing borrows from the input, which fails for identifiers and
+// comments with escapes in JSON; `OwnedToken` reads the same JSON back
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token<'src> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub kind: TokenKind<'src>,
+    // exact source text, quotes and escapes included; always borrowed when
+    // scanned, but owned after deserializing an escaped one
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub lexeme: Cow<'src, str>,
     pub span: SourceSpan,
 }
 
+impl Token<'_> {
+    // slices by `bytes_range`, since `char_range` can't index a `str`; for
+    // tokens that aren't from `source` itself, like deserialized ones
+    pub fn lexeme_in<'a>(&self, source: &'a str) -> &'a str {
+        source
+            .get(Range::from(self.span.bytes_range))
+            .unwrap_or_default()
+    }
+}
+
 impl std::fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("Token::{:?}", self.kind))?;
-
-        if self.span.is_char() {
-            f.write_fmt(format_args!("@{}", self.span.char_start()))?;
-        } else {
-            f.write_fmt(format_args!(
-                "@{}..{}",
-                self.span.char_start(),
-                self.span.char_end()
-            ))?;
+        write!(f, "Token::{:?}@{}", self.kind, self.span.char_range)
+    }
+}
+
+// by span; kinds have no order of their own, so two different tokens with
+// the same span are unordered and tokens aren't `Ord`; to sort tokens
+// `sort_by(|a, b| a.span.cmp(&b.span))` never has to give up
+impl PartialOrd for Token<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match self.span.cmp(&other.span) {
+            std::cmp::Ordering::Equal => (self == other).then_some(std::cmp::Ordering::Equal),
+            ordering => Some(ordering),
         }
+    }
+}
 
-        Ok(())
+// a token that owns its text, to be sent to another thread or kept after
+// the source is gone
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedToken {
+    pub kind: OwnedTokenKind,
+    pub lexeme: String,
+    pub span: SourceSpan,
+}
+
+impl Token<'_> {
+    pub fn to_owned(&self) -> OwnedToken {
+        OwnedToken {
+            kind: OwnedTokenKind::from(&self.kind),
+            lexeme: self.lexeme.to_string(),
+            span: self.span.clone(),
+        }
+    }
+}
+
+impl OwnedToken {
+    pub fn as_token(&self) -> Token<'_> {
+        Token {
+            kind: self.kind.as_kind(),
+            lexeme: Cow::Borrowed(&self.lexeme),
+            span: self.span.clone(),
+        }
     }
 }
+
+// `FromStr` can't hand out a borrow of the input, so it lives on the owned
+// kind, with `From<&str>` for `TokenKind`
+impl std::str::FromStr for OwnedTokenKind {
+    type Err = std::convert::Infallible;
+
+    fn from_str(word: &str) -> Result<Self, Self::Err> {
+        Ok(OwnedTokenKind::from(&TokenKind::from(word)))
+    }
+}
+
+// variants without data are listed once and mapped one to one
+macro_rules! owned_token_kind {
+    ($($unit:ident),* $(,)?) => {
+        #[derive(Debug, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum OwnedTokenKind {
+            $($unit,)*
+            Identifier(String),
+            String(String),
+            Integer(i64),
+            Number(f64),
+            LineComment(String),
+            BlockComment(String),
+        }
+
+        impl From<&TokenKind<'_>> for OwnedTokenKind {
+            fn from(kind: &TokenKind<'_>) -> Self {
+                match kind {
+                    $(TokenKind::$unit => Self::$unit,)*
+                    TokenKind::Identifier(s) => Self::Identifier(s.to_string()),
+                    TokenKind::String(s) => Self::String(s.to_string()),
+                    TokenKind::Integer(n) => Self::Integer(*n),
+                    TokenKind::Number(n) => Self::Number(*n),
+                    TokenKind::LineComment(s) => Self::LineComment(s.to_string()),
+                    TokenKind::BlockComment(s) => Self::BlockComment(s.to_string()),
+                }
+            }
+        }
+
+        // same as `TokenKind`, as for `NaN`
+        impl PartialEq for OwnedTokenKind {
+            fn eq(&self, other: &Self) -> bool {
+                self.as_kind() == other.as_kind()
+            }
+        }
+
+        impl Eq for OwnedTokenKind {}
+
+        impl OwnedTokenKind {
+            pub fn as_kind(&self) -> TokenKind<'_> {
+                match self {
+                    $(Self::$unit => TokenKind::$unit,)*
+                    Self::Identifier(s) => TokenKind::Identifier(s),
+                    Self::String(s) => TokenKind::String(Cow::Borrowed(s)),
+                    Self::Integer(n) => TokenKind::Integer(*n),
+                    Self::Number(n) => TokenKind::Number(*n),
+                    Self::LineComment(s) => TokenKind::LineComment(s),
+                    Self::BlockComment(s) => TokenKind::BlockComment(s),
+                }
+            }
+        }
+    };
+}
+
+owned_token_kind!(
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Comma,
+    Dot,
+    Minus,
+    Percent,
+    Plus,
+    Question,
+    Semicolon,
+    Slash,
+    Star,
+    Bang,
+    BangEqual,
+    Equal,
+    EqualEqual,
+    FatArrow,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    And,
+    Break,
+    Class,
+    Continue,
+    Else,
+    False,
+    Fun,
+    For,
+    If,
+    Nil,
+    Or,
+    Print,
+    Return,
+    Super,
+    This,
+    True,
+    Var,
+    While,
+    Eof
+);
@@ -0,0 +1,101 @@
+#![cfg(feature = "tokio")]
+
+use loxide::{async_scanner::AsyncScanner, scanner::Scanner};
+use tokio::io::AsyncReadExt;
+
+fn single_shot(source: &str) -> Vec<String> {
+    Scanner::scan(source)
+        .map(|item| match item {
+            Ok(token) => format!("{:?} {:?}", token.to_owned(), token.lexeme),
+            Err(err) => err.to_string(),
+        })
+        .collect()
+}
+
+async fn chunked(chunks: &[&'static str]) -> Vec<String> {
+    let mut reader: Box<dyn tokio::io::AsyncRead + Unpin> = Box::new(&b""[..]);
+    for chunk in chunks {
+        reader = Box::new(reader.chain(chunk.as_bytes()));
+    }
+    AsyncScanner::new(reader)
+        .collect()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|item| match item {
+            Ok(token) => format!("{token:?} {:?}", token.lexeme),
+            Err(err) => err.to_string(),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn two_chunks() {
+    let source = "var answer = 4.25;\nprint \"a + b\" + answer; @";
+    for split in 0..=source.len() {
+        let (first, second) = source.split_at(split);
+        let (first, second): (&'static str, &'static str) = (
+            Box::leak(first.to_string().into_boxed_str()),
+            Box::leak(second.to_string().into_boxed_str()),
+        );
+        assert_eq!(
+            chunked(&[first, second]).await,
+            single_shot(source),
+            "split at {split}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn straddling_tokens() {
+    let cases: &[&[&'static str]] = &[
+        &["pri", "nt 1;"],
+        &["1.", "5"],
+        &["1", "e10"],
+        &["\"unfinished ", "string\""],
+        &["/* a ", "comment */ x"],
+        &["a =", "> b == c"],
+        &["\"", "\"\"triple\"\"\""],
+        &["\"caf\u{e9}", "\""],
+    ];
+    for chunks in cases {
+        assert_eq!(
+            chunked(chunks).await,
+            single_shot(&chunks.concat()),
+            "{chunks:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn leading_bom() {
+    let chunks = ["\u{feff}pr", "int 1;"];
+    let items = chunked(&chunks).await;
+    assert_eq!(items, single_shot(&chunks.concat()));
+    assert!(
+        items.iter().all(|item| !item.contains("Unexpected")),
+        "{items:?}"
+    );
+}
+
+#[tokio::test]
+async fn token_over_many_chunks() {
+    let mut chunks = vec!["print \""];
+    chunks.extend(["long "; 200]);
+    chunks.push("\";");
+    assert_eq!(chunked(&chunks).await, single_shot(&chunks.concat()));
+}
+
+#[tokio::test]
+async fn char_cut_in_half() {
+    let source = "print \"é€\";".as_bytes();
+    let mut reader = &source[..8];
+    let rest = &source[8..];
+    let reader = (&mut reader).chain(rest);
+    let items = AsyncScanner::new(reader).collect().await.unwrap();
+    let lexemes = items
+        .iter()
+        .map(|item| item.as_ref().unwrap().lexeme.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(lexemes, ["print", "\"é€\"", ";", ""]);
+}
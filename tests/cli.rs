@@ -0,0 +1,343 @@
+use assert_cmd::Command;
+
+fn loxide() -> Command {
+    Command::cargo_bin("loxide").unwrap()
+}
+
+#[test]
+fn tokens_are_not_printed_by_default() {
+    let output = loxide().write_stdin("1 + 2;\n").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Tokens"), "{stdout}");
+
+    let output = loxide()
+        .arg("--print-tokens")
+        .write_stdin("1 + 2;\n")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Tokens"), "{stdout}");
+}
+
+#[test]
+fn read_script_from_stdin() {
+    // a block over several lines would not parse line by line in the REPL
+    let script = "var a = 1;\n{\n  print a + 2;\n}\n";
+
+    let output = loxide()
+        .args(["--plain", "-"])
+        .write_stdin(script)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let output = loxide()
+        .arg("--plain")
+        .write_stdin(script)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn eval_inline_script() {
+    let output = loxide()
+        .args(["--plain", "-e", "print 1 + 2;"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let output = loxide()
+        .args(["--plain", "--eval", "1 + 2"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn eval_with_file_is_rejected() {
+    let output = loxide()
+        .args(["-e", "1 + 2", "script.lox"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Usage:"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn output_tokens_as_json() {
+    use loxide::{HandleLoxResultIter, Scanner, Token, token::OwnedToken};
+
+    let script = "var a = \"hi\" + \"\\\"!\";\n";
+    let output = loxide()
+        .args(["--output", "json"])
+        .write_stdin(script)
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let tokens: Vec<OwnedToken> = serde_json::from_str(&stdout).unwrap();
+    let scanned = Scanner::scan(script).process_silent().0;
+    assert_eq!(
+        tokens,
+        scanned.iter().map(Token::to_owned).collect::<Vec<_>>()
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_output_is_not_followed_by_program_output() {
+    let output = loxide()
+        .args(["--output", "json", "-e", "print 1;"])
+        .output()
+        .unwrap();
+
+    let tokens: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tokens.as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn print_statement_output() {
+    loxide()
+        .arg("--plain")
+        .write_stdin("print 1 + 2;\n")
+        .assert()
+        .success()
+        .stdout("3\n");
+}
+
+#[test]
+fn errors_go_to_stderr() {
+    let output = loxide()
+        .args(["--plain", "--print-tokens"])
+        .write_stdin("print 1 + @;\n")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("Tokens"), "{stdout}");
+    assert!(!stdout.contains("Unexpected character"), "{stdout}");
+    assert!(!stdout.contains("errors"), "{stdout}");
+    assert!(stderr.contains("Unexpected character '@'"), "{stderr}");
+    assert!(stderr.contains("Parsing errors:"), "{stderr}");
+}
+
+#[test]
+fn max_errors_caps_diagnostics() {
+    let output = loxide()
+        .args(["--plain", "--max-errors", "2"])
+        .write_stdin("@ @ @ @\n")
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.matches("Unexpected character").count(),
+        2,
+        "{stderr}"
+    );
+    assert!(stderr.contains("2 more errors"), "{stderr}");
+}
+
+#[test]
+fn max_errors_is_shared_between_stages() {
+    let output = loxide()
+        .args(["--plain", "--max-errors", "1", "-e", "@ @; 1 +"])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.matches("Unexpected character").count(),
+        1,
+        "{stderr}"
+    );
+    assert_eq!(stderr.matches("more error").count(), 1, "{stderr}");
+    assert!(stderr.contains("2 more errors"), "{stderr}");
+}
+
+#[test]
+fn count_tokens_summary() {
+    let output = loxide()
+        .args(["--plain", "--count-tokens", "-e", "print 1 + 2 + x;"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let counts = stdout.lines().skip(1).collect::<Vec<_>>();
+    assert_eq!(
+        counts,
+        [
+            "    2: integer",
+            "    2: plus",
+            "    1: identifier",
+            "    1: print",
+            "    1: semicolon"
+        ]
+    );
+    // the script isn't run, so there's no undefined variable
+    assert!(output.stderr.is_empty());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn diagnostics_as_json() {
+    let output = loxide()
+        .args(["--diagnostics", "json", "-e", "print 1 @ + 2 $;"])
+        .output()
+        .unwrap();
+
+    assert!(output.stdout.is_empty());
+    let diagnostics: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(
+        diagnostics,
+        serde_json::json!([
+            {
+                "code": "E0001",
+                "message": "Unexpected character '@'",
+                "line": 1,
+                "column": 8,
+                "span": { "start": 8, "end": 9 },
+            },
+            {
+                "code": "E0001",
+                "message": "Unexpected character '$'",
+                "line": 1,
+                "column": 14,
+                "span": { "start": 14, "end": 15 },
+            },
+        ])
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn diagnostics_as_json_without_errors() {
+    let output = loxide()
+        .args(["--plain", "--diagnostics", "json", "-e", "print 1;"])
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    let diagnostics: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(diagnostics, serde_json::json!([]));
+}
+
+#[cfg(not(feature = "serde"))]
+#[test]
+fn diagnostics_as_json_needs_serde() {
+    let output = loxide()
+        .args(["--diagnostics", "json", "-e", "print 1;"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(64));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn print_empty_string() {
+    loxide()
+        .args(["--plain", "-e", "print \"\";"])
+        .assert()
+        .success()
+        .stdout("\"\"\n")
+        .stderr("");
+}
+
+#[test]
+fn context_lines_around_errors() {
+    let output = loxide()
+        .args([
+            "--plain",
+            "--context-lines",
+            "1",
+            "-e",
+            "var a;\nprint @;\nprint a;",
+        ])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 │ var a;"), "{stderr}");
+    assert!(stderr.contains("3 │ print a;"), "{stderr}");
+}
+
+#[test]
+fn output_tokens_grouped() {
+    let output = loxide()
+        .args(["--plain", "--output", "grouped", "-e", "print 1;\nx;"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout.lines().take(7).collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "─── Tokens ───",
+            "line 1: print 1;",
+            "  00: Token::Print@0..5",
+            "  01: Token::Integer(1)@6",
+            "  02: Token::Semicolon@7",
+            "line 2: x;",
+            "  03: Token::Identifier(\"x\")@9",
+        ]
+    );
+}
+
+#[test]
+fn missing_semicolon_after_multiline_string() {
+    let output = loxide()
+        .args(["--plain", "-e", "print \"a\nbc\"\nx"])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("[2:3] Expected ';' after print statement"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn stream_caps_errors() {
+    let output = loxide()
+        .args([
+            "--plain",
+            "--stream",
+            "--max-errors",
+            "1",
+            "-e",
+            "print @ $ % ;",
+        ])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.matches("Unexpected character").count(),
+        1,
+        "{stderr}"
+    );
+    assert!(stderr.contains("1 more error"), "{stderr}");
+    assert!(stderr.contains("Parsing errors: 2"), "{stderr}");
+}
+
+#[test]
+fn stream_rejects_other_formats() {
+    for args in [
+        ["--output", "json"],
+        ["--output", "grouped"],
+        ["--diagnostics", "json"],
+    ] {
+        let output = loxide()
+            .args(["--stream", "-e", "1;"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(64), "{args:?}");
+    }
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "miette")]
+
+use loxide::{diagnostic, scanner::Scanner, source::Source};
+use miette::{GraphicalReportHandler, GraphicalTheme};
+
+fn render(report: &miette::Report) -> String {
+    let mut out = String::new();
+    GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+        .render_report(&mut out, report.as_ref())
+        .unwrap();
+    out
+}
+
+#[test]
+fn report_scanner_errors() {
+    let source = Source {
+        script: "var a = \"oops;",
+        location: None,
+    };
+    let report = diagnostic::report(Scanner::scan(&source), &source).unwrap();
+    let out = render(&report);
+
+    assert!(out.contains("E0008"), "{out}");
+    assert!(out.contains("╰── Unterminated string, started here"), "{out}");
+    assert!(out.contains("var a = \"oops;"), "{out}");
+}
+
+#[test]
+fn report_without_errors() {
+    let source = Source {
+        script: "var a = 1;",
+        location: None,
+    };
+    assert!(diagnostic::report(Scanner::scan(&source), &source).is_none());
+}
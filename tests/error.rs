@@ -0,0 +1,172 @@
+use std::ffi::OsStr;
+
+use loxide::{
+    error::{Error, RenderOptions, colors_enabled, render_diagnostic, render_diagnostic_with},
+    scanner::Scanner,
+    source::{LineIndex, SourceFile},
+};
+
+#[test]
+fn colors_respect_no_color() {
+    // output piped from the binary never has colors, so this is about
+    // NO_COLOR alone on a terminal
+    assert!(colors_enabled(true, None, true));
+    assert!(colors_enabled(true, Some(OsStr::new("")), true));
+    assert!(!colors_enabled(true, Some(OsStr::new("1")), true));
+    assert!(!colors_enabled(true, None, false));
+}
+
+#[test]
+fn colors_respect_no_color_flag() {
+    assert!(!colors_enabled(false, None, true));
+    assert!(!colors_enabled(false, Some(OsStr::new("")), true));
+
+    yansi::disable();
+    let err = Scanner::scan("1 + @;").find_map(Result::err).unwrap();
+    assert!(!render_diagnostic(&err).contains('\x1b'));
+}
+
+#[test]
+fn render_unexpected_character() {
+    yansi::disable();
+
+    let err = Scanner::scan("1 + @;").find_map(Result::err).unwrap();
+    assert_eq!(
+        render_diagnostic(&err),
+        "▓ 1 + @;\n░     ~\n░ [1:4] Unexpected character '@'"
+    );
+}
+
+#[test]
+fn render_expands_tabs() {
+    yansi::disable();
+
+    let options = RenderOptions {
+        tab_width: 4,
+        ..RenderOptions::default()
+    };
+    let script = "\tprint @;";
+    let err = Scanner::scan(script).find_map(Result::err).unwrap();
+    assert_eq!(
+        render_diagnostic_with(&err, &LineIndex::new(script), &options),
+        "▓     print @;\n░           ~\n░ [1:7] Unexpected character '@'"
+    );
+
+    // a tab only goes up to the next tab stop
+    let script = "a\tb @";
+    let err = Scanner::scan(script).find_map(Result::err).unwrap();
+    assert_eq!(
+        render_diagnostic_with(&err, &LineIndex::new(script), &options),
+        "▓ a   b @\n░       ~\n░ [1:4] Unexpected character '@'"
+    );
+    assert!(render_diagnostic(&err).starts_with("▓ a       b @\n░           ~\n"));
+}
+
+#[test]
+fn missing_file_has_io_source() {
+    use std::error::Error as _;
+
+    let err = SourceFile::read("no/such/file.lox").unwrap_err();
+    assert!(matches!(err, Error::Io(_)));
+    let source = err.source().unwrap();
+    let io = source.downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(io.kind(), std::io::ErrorKind::NotFound);
+
+    let err = Error::from(Scanner::scan("@").find_map(Result::err).unwrap());
+    assert!(matches!(err, Error::Lox(_)));
+    assert!(err.source().is_none());
+    assert_eq!(err.to_string(), "[1:0] Unexpected character '@'");
+}
+
+#[test]
+fn render_context_lines() {
+    yansi::disable();
+
+    let options = RenderOptions {
+        context_lines: 1,
+        ..RenderOptions::default()
+    };
+    let script = "var a = 1;\nvar b = 2;\nprint a + @;\nprint b;\nprint a;";
+    let err = Scanner::scan(script).find_map(Result::err).unwrap();
+    let rendered = render_diagnostic_with(&err, &LineIndex::new(script), &options);
+    assert_eq!(
+        rendered,
+        "░ 2 │ var b = 2;\n\
+         ▓ 3 │ print a + @;\n\
+         ░   │           ~\n\
+         ░ 4 │ print b;\n\
+         ░ [3:10] Unexpected character '@'"
+    );
+    // an index for another source isn't used
+    assert_eq!(
+        render_diagnostic_with(&err, &LineIndex::new("@"), &options),
+        rendered
+    );
+
+    // context stops at the ends of the source
+    let options = RenderOptions {
+        context_lines: 3,
+        ..RenderOptions::default()
+    };
+    let script = "@\nprint 1;";
+    let err = Scanner::scan(script).find_map(Result::err).unwrap();
+    assert_eq!(
+        render_diagnostic_with(&err, &LineIndex::new(script), &options),
+        "▓ 1 │ @\n░   │ ~\n░ 2 │ print 1;\n░ [1:0] Unexpected character '@'"
+    );
+}
+
+#[test]
+fn error_codes() {
+    use std::borrow::Cow;
+
+    use loxide::{error::LoxErrorKind, token::TokenKind};
+
+    let kinds = [
+        (LoxErrorKind::UnexpectedCharacter('@'), "E0001"),
+        (LoxErrorKind::UnexpectedEof, "E0002"),
+        (LoxErrorKind::UnexpectedToken(TokenKind::Semicolon), "E0003"),
+        (LoxErrorKind::UndefinedVariable(Cow::Borrowed("a")), "E0004"),
+        (LoxErrorKind::Expected("';'"), "E0005"),
+        (LoxErrorKind::ExpectedExpr, "E0006"),
+        (LoxErrorKind::ExpectedValues(&["number"]), "E0007"),
+        (LoxErrorKind::UnterminatedString, "E0008"),
+        (LoxErrorKind::UnterminatedComment, "E0009"),
+        (LoxErrorKind::InvalidEscape { c: 'q' }, "E0010"),
+        (LoxErrorKind::InvalidUnicodeEscape { value: "zz" }, "E0011"),
+        (LoxErrorKind::InvalidNumber("1e".to_string()), "E0012"),
+        (LoxErrorKind::InvalidNumberSuffix { suffix: "abc" }, "E0013"),
+        (LoxErrorKind::InvalidConversion("nil", "number"), "E0014"),
+        (LoxErrorKind::InvalidAssignmentTarget, "E0015"),
+        (LoxErrorKind::InvalidOffset { offset: 1 }, "E0016"),
+        (
+            LoxErrorKind::InvalidKeyword {
+                word: "one",
+                kind: "number",
+            },
+            "E0017",
+        ),
+        (LoxErrorKind::Unreachable, "E0018"),
+        (LoxErrorKind::IdentifierTooLong { len: 2, max: 1 }, "E0019"),
+        (LoxErrorKind::StringTooLong { len: 2, max: 1 }, "E0020"),
+        (LoxErrorKind::LimitExceeded, "E0021"),
+    ];
+    for (kind, code) in &kinds {
+        assert_eq!(kind.code(), *code);
+    }
+
+    let err = Scanner::scan("1 + @;").find_map(Result::err).unwrap();
+    assert_eq!(err.to_string(), "[1:4] Unexpected character '@'");
+    assert_eq!(format!("{err:#}"), "[1:4] [E0001] Unexpected character '@'");
+
+    yansi::disable();
+    let options = RenderOptions {
+        error_codes: true,
+        ..RenderOptions::default()
+    };
+    assert!(render_diagnostic(&err).ends_with("\n░ [1:4] Unexpected character '@'"));
+    assert!(
+        render_diagnostic_with(&err, &LineIndex::new("1 + @;"), &options)
+            .ends_with("\n░ [1:4] [E0001] Unexpected character '@'")
+    );
+}
@@ -1,8 +1,9 @@
 use loxide::{
     ast::{Expr, ExprKind, Stmt, StmtKind},
     environment::Environment,
+    error::LoxErrorKind,
     interpreter::{Interpreter, LoxValue},
-    source::SourceSpan,
+    source::{SourceSpan, Span},
     token::TokenKind,
 };
 
@@ -11,8 +12,9 @@ fn expr(kind: ExprKind<'static>) -> Expr<'static> {
         kind,
         SourceSpan {
             line: 0,
-            char_range: 0..=0,
-            bytes_range: 0..=0,
+            column: 0,
+            char_range: Span::new(0, 1),
+            bytes_range: Span::new(0, 1),
         },
     )
 }
@@ -22,8 +24,9 @@ fn stmt(kind: StmtKind<'static>) -> Stmt<'static> {
         kind,
         SourceSpan {
             line: 0,
-            char_range: 0..=0,
-            bytes_range: 0..=0,
+            column: 0,
+            char_range: Span::new(0, 1),
+            bytes_range: Span::new(0, 1),
         },
     )
 }
@@ -33,7 +36,7 @@ fn num(n: f64) -> Expr<'static> {
 }
 
 fn string(s: &'static str) -> Expr<'static> {
-    expr(ExprKind::LitString(s))
+    expr(ExprKind::LitString(s.into()))
 }
 
 fn boolean(b: bool) -> Expr<'static> {
@@ -342,3 +345,55 @@ fn interpret_if_else() {
     let (value, _env) = interpret_stmt(conditional_stmt);
     assert!(matches!(value, LoxValue::Number(2.0)));
 }
+
+#[test]
+fn interpret_type_errors() {
+    let mut env = Environment::default();
+    let minus = stmt(StmtKind::ExprReturn(Box::new(binary(
+        string("a"),
+        TokenKind::Minus,
+        num(1.0),
+    ))));
+    let err = Interpreter::execute_many([minus], "", &mut env).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        LoxErrorKind::InvalidConversion("string", "number")
+    ));
+
+    let plus = stmt(StmtKind::ExprReturn(Box::new(binary(
+        string("a"),
+        TokenKind::Plus,
+        num(1.0),
+    ))));
+    let err = Interpreter::execute_many([plus], "", &mut env).unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::ExpectedValues(_)));
+}
+
+#[test]
+fn interpret_undefined_variable() {
+    let mut env = Environment::default();
+    let read = stmt(StmtKind::ExprReturn(Box::new(var_expr("nope"))));
+    let err = Interpreter::execute_many([read], "", &mut env).unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::UndefinedVariable(name) if name == "nope"));
+
+    let write = stmt(StmtKind::ExprReturn(Box::new(assign("nope", num(1.0)))));
+    let err = Interpreter::execute_many([write], "", &mut env).unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::UndefinedVariable(name) if name == "nope"));
+}
+
+#[test]
+fn interpret_redeclaration_shadows() {
+    let first = var_decl("x", Some(num(1.0)));
+    let second = var_decl("x", Some(string("two")));
+    let read = stmt(StmtKind::ExprReturn(Box::new(var_expr("x"))));
+    let mut env = Environment::default();
+    let value = Interpreter::execute_many([first, second, read], "", &mut env).unwrap();
+    assert_eq!(value, LoxValue::String("two".into()));
+}
+
+#[test]
+fn display_numbers() {
+    assert_eq!(LoxValue::Number(3.0).to_string(), "3");
+    assert_eq!(LoxValue::Number(-0.5).to_string(), "-0.5");
+    assert_eq!(LoxValue::Number(1e-17).to_string(), "0.00000000000000001");
+}
@@ -1,18 +1,21 @@
 use loxide::{
     ast::{Expr, ExprKind, Stmt, StmtKind},
-    error::HandleLoxResultIter,
+    error::{HandleLoxResultIter, LoxErrorKind},
     parser::Parser,
-    source::SourceSpan,
+    scanner::Scanner,
+    source::{SourceSpan, Span},
     token::{Token, TokenKind},
 };
 
 fn t(kind: TokenKind<'static>) -> Token<'static> {
     Token {
         kind,
+        lexeme: "".into(),
         span: SourceSpan {
             line: 0,
-            char_range: 0..=0,
-            bytes_range: 0..=0,
+            column: 0,
+            char_range: Span::new(0, 1),
+            bytes_range: Span::new(0, 1),
         },
     }
 }
@@ -38,8 +41,11 @@ fn parse_literals() {
     let expr = parse_single(vec![t(TokenKind::Number(42.0)), t(TokenKind::Eof)]);
     assert!(matches!(expr.kind, ExprKind::LitNumber(42.0)));
 
-    let expr = parse_single(vec![t(TokenKind::String("hello")), t(TokenKind::Eof)]);
-    assert!(matches!(expr.kind, ExprKind::LitString("hello")));
+    let expr = parse_single(vec![
+        t(TokenKind::String("hello".into())),
+        t(TokenKind::Eof),
+    ]);
+    assert!(matches!(expr.kind, ExprKind::LitString(s) if s == "hello"));
 
     let expr = parse_single(vec![t(TokenKind::True), t(TokenKind::Eof)]);
     assert!(matches!(expr.kind, ExprKind::LitBoolean(true)));
@@ -483,3 +489,138 @@ fn parse_if_else_statement() {
         _ => panic!("expected Conditional, got {:?}", stmts[0].kind),
     }
 }
+
+#[test]
+fn parse_left_associative() {
+    let expr = parse_single(vec![
+        t(TokenKind::Number(1.0)),
+        t(TokenKind::Minus),
+        t(TokenKind::Number(2.0)),
+        t(TokenKind::Minus),
+        t(TokenKind::Number(3.0)),
+        t(TokenKind::Eof),
+    ]);
+    match expr.kind {
+        ExprKind::Binary { left, right, .. } => {
+            assert!(matches!(left.kind, ExprKind::Binary { .. }));
+            assert!(matches!(right.kind, ExprKind::LitNumber(3.0)));
+        }
+        _ => panic!("expected Binary, got {:?}", expr.kind),
+    }
+}
+
+#[test]
+fn parse_trailing_operator() {
+    let tokens = vec![
+        t(TokenKind::Number(1.0)),
+        t(TokenKind::Plus),
+        t(TokenKind::Eof),
+    ];
+    let (stmts, errors) = Parser::parse(tokens, "").collect_errors();
+    assert!(stmts.is_empty());
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::UnexpectedToken(TokenKind::Eof)
+    ));
+}
+
+#[test]
+fn parse_to_sexpr() {
+    let expr = parse_single(vec![
+        t(TokenKind::Number(1.0)),
+        t(TokenKind::Plus),
+        t(TokenKind::LeftParen),
+        t(TokenKind::Minus),
+        t(TokenKind::Number(2.5)),
+        t(TokenKind::Star),
+        t(TokenKind::String("s".into())),
+        t(TokenKind::RightParen),
+        t(TokenKind::Eof),
+    ]);
+    assert_eq!(expr.to_sexpr(), r#"(+ 1 (group (* (- 2.5) "s")))"#);
+
+    let stmts = parse(vec![
+        t(TokenKind::Print),
+        t(TokenKind::Identifier("a")),
+        t(TokenKind::Or),
+        t(TokenKind::Nil),
+        t(TokenKind::Semicolon),
+        t(TokenKind::Eof),
+    ]);
+    assert_eq!(stmts[0].to_sexpr(), "(print (or a nil))");
+
+    let stmts = parse(vec![
+        t(TokenKind::Print),
+        t(TokenKind::String("a\"b\nc\\\u{1}".into())),
+        t(TokenKind::Semicolon),
+        t(TokenKind::Eof),
+    ]);
+    assert_eq!(stmts[0].to_sexpr(), r#"(print "a\"b\nc\\\u{1}")"#);
+}
+
+#[test]
+fn parse_missing_semicolon() {
+    let source = "print 1 print 2;";
+    let (tokens, _) = Scanner::scan(source).process_silent();
+    let (_, errors) = Parser::parse(tokens, source).collect_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind, LoxErrorKind::Expected(_)));
+    // right after `1`, not at the next statement
+    assert_eq!(errors[0].span.column, 7);
+}
+
+#[test]
+fn parse_error_at_eof() {
+    let source = "var a = 1;\nprint a +";
+    let (tokens, _) = Scanner::scan(source).process_silent();
+    let (_, errors) = Parser::parse(tokens, source).collect_errors();
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::UnexpectedToken(TokenKind::Eof)
+    ));
+    assert_eq!(errors[0].span.line, 2);
+    assert_eq!(errors[0].span.column, 9);
+    assert_eq!(errors[0].span.char_start(), source.len());
+}
+
+#[test]
+fn parse_missing_semicolon_after_lines() {
+    let source = "print 1\n+ 2\nprint 3;";
+    let (tokens, _) = Scanner::scan(source).process_silent();
+    let (_, errors) = Parser::parse(tokens, source).collect_errors();
+    assert!(matches!(errors[0].kind, LoxErrorKind::Expected(_)));
+    // right after `2`, on the line it's on
+    assert_eq!(errors[0].span.line, 2);
+    assert_eq!(errors[0].span.column, 3);
+}
+
+#[test]
+fn parse_missing_semicolon_after_multiline_string() {
+    let missing = |source| {
+        let (tokens, _) = Scanner::scan(source).process_silent();
+        let (_, errors) = Parser::parse(tokens, source).collect_errors();
+        assert!(matches!(errors[0].kind, LoxErrorKind::Expected(_)));
+        (errors[0].span.line, errors[0].span.column)
+    };
+
+    // right after the closing quote, on the line the string ends on
+    assert_eq!(missing("print \"a\nbc\"\nx"), (2, 3));
+    assert_eq!(missing("print \"\"\"\nabc\nde\"\"\"\nx"), (3, 5));
+}
+
+#[test]
+fn parse_literal_spans_end_after_the_token() {
+    let source = "print 1 + abc;";
+    let (tokens, _) = Scanner::scan(source).process_silent();
+    let (stmts, _) = Parser::parse(tokens, source).collect_errors();
+    let StmtKind::Print(expr) = &stmts[0].kind else {
+        panic!("{:?}", stmts[0]);
+    };
+    let ExprKind::Binary { left, right, .. } = &expr.kind else {
+        panic!("{expr:?}");
+    };
+    assert_eq!(left.span.char_range, Span::new(6, 7));
+    assert_eq!(right.span.char_range, Span::new(10, 13));
+    assert_eq!(right.span.column, 10);
+}
@@ -0,0 +1,9 @@
+use loxide::repl::{MetaCommand, meta_command};
+
+#[test]
+fn repl_meta_commands() {
+    assert_eq!(meta_command(":quit"), Some(MetaCommand::Quit));
+    assert_eq!(meta_command("  :exit\n"), Some(MetaCommand::Quit));
+    assert_eq!(meta_command(":help"), Some(MetaCommand::Unknown("help")));
+    assert_eq!(meta_command("print 1;"), None);
+}
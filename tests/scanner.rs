@@ -1,28 +1,47 @@
+use std::borrow::Cow;
+
 use loxide::{
-    error::HandleLoxResultIter,
-    scanner::Scanner,
-    source::SourceSpan,
-    token::{Token, TokenKind},
+    error::{
+        HandleLoxResultIter, LoxError, LoxErrorKind, LoxWarning, LoxWarningKind, RenderOptions,
+    },
+    scanner::{
+        OwnedScanner, Scanner, TokenStream, dangling_dots, needs_more_input, print_tokens_streaming,
+    },
+    source::{Position, SourceFile, SourceSpan, Span},
+    token::{Token, TokenKind, write_token},
 };
 
-fn t(kind: TokenKind<'_>, start: usize, end: usize) -> Token<'_> {
+// ascii-only, so the lexeme length gives both char and byte ranges
+fn t<'src>(kind: TokenKind<'src>, lexeme: &'src str, start: usize) -> Token<'src> {
+    let end = start + lexeme.len();
     Token {
         kind,
+        lexeme: lexeme.into(),
         span: SourceSpan {
-            line: 0,
-            char_range: start..=end,
-            bytes_range: start..=end,
+            line: 1,
+            column: start,
+            char_range: Span::new(start, end),
+            bytes_range: Span::new(start, end),
         },
     }
 }
 
-fn t_line(kind: TokenKind<'_>, line: usize, start: usize, end: usize) -> Token<'_> {
+fn t_line<'src>(
+    kind: TokenKind<'src>,
+    lexeme: &'src str,
+    line: usize,
+    column: usize,
+    start: usize,
+) -> Token<'src> {
+    let end = start + lexeme.len();
     Token {
         kind,
+        lexeme: lexeme.into(),
         span: SourceSpan {
             line,
-            char_range: start..=end,
-            bytes_range: start..=end,
+            column,
+            char_range: Span::new(start, end),
+            bytes_range: Span::new(start, end),
         },
     }
 }
@@ -30,21 +49,25 @@ fn t_line(kind: TokenKind<'_>, line: usize, start: usize, end: usize) -> Token<'
 fn eof(pos: usize) -> Token<'static> {
     Token {
         kind: TokenKind::Eof,
+        lexeme: "".into(),
         span: SourceSpan {
-            line: 0,
-            char_range: pos..=pos,
-            bytes_range: pos..=pos,
+            line: 1,
+            column: pos,
+            char_range: Span::new(pos, pos),
+            bytes_range: Span::new(pos, pos),
         },
     }
 }
 
-fn eof_line(line: usize, pos: usize) -> Token<'static> {
+fn eof_line(line: usize, column: usize, pos: usize) -> Token<'static> {
     Token {
         kind: TokenKind::Eof,
+        lexeme: "".into(),
         span: SourceSpan {
             line,
-            char_range: pos..=pos,
-            bytes_range: pos..=pos,
+            column,
+            char_range: Span::new(pos, pos),
+            bytes_range: Span::new(pos, pos),
         },
     }
 }
@@ -53,36 +76,720 @@ fn scan(input: &str) -> (Vec<Token<'_>>, usize) {
     Scanner::scan(input).process_silent()
 }
 
+fn scan_errors(input: &str) -> Vec<LoxError<'_>> {
+    Scanner::scan(input).filter_map(Result::err).collect()
+}
+
 #[test]
 fn scan_number() {
     assert_eq!(
         scan("300.003").0,
-        vec![t(TokenKind::Number(300.003), 0, 6), eof(7)]
+        vec![t(TokenKind::Number(300.003), "300.003", 0), eof(7)]
+    );
+    assert_eq!(
+        scan("69").0,
+        vec![t(TokenKind::Integer(69), "69", 0), eof(2)]
     );
-    assert_eq!(scan("69").0, vec![t(TokenKind::Number(69.0), 0, 1), eof(2)]);
-    assert_eq!(scan("0").0, vec![t(TokenKind::Number(0.0), 0, 0), eof(1)]);
+    assert_eq!(scan("0").0, vec![t(TokenKind::Integer(0), "0", 0), eof(1)]);
     assert_eq!(
         scan("123456789").0,
-        vec![t(TokenKind::Number(123456789.0), 0, 8), eof(9)]
+        vec![t(TokenKind::Integer(123456789), "123456789", 0), eof(9)]
+    );
+    assert_eq!(
+        scan("0.5").0,
+        vec![t(TokenKind::Number(0.5), "0.5", 0), eof(3)]
+    );
+}
+
+#[test]
+fn scan_number_integer_or_float() {
+    let (tokens, _) = scan("3 3.0");
+    assert_eq!(tokens[0].kind, TokenKind::Integer(3));
+    assert_eq!(tokens[1].kind, TokenKind::Number(3.0));
+    assert_ne!(tokens[0].kind, tokens[1].kind);
+    assert_eq!(tokens[0].kind.to_string(), "3");
+    assert_eq!(tokens[1].kind.to_string(), "3.0");
+
+    // past `i64`, but still a valid number
+    assert_eq!(
+        scan("99999999999999999999").0[0].kind,
+        TokenKind::Number(99999999999999999999.0)
+    );
+}
+
+#[test]
+fn scan_number_exponent() {
+    assert_eq!(
+        scan("1e10").0,
+        vec![t(TokenKind::Number(1e10), "1e10", 0), eof(4)]
+    );
+    assert_eq!(
+        scan("1E+3").0,
+        vec![t(TokenKind::Number(1e3), "1E+3", 0), eof(4)]
+    );
+    assert_eq!(
+        scan("2.5e-3").0,
+        vec![t(TokenKind::Number(2.5e-3), "2.5e-3", 0), eof(6)]
+    );
+}
+
+#[test]
+fn scan_number_malformed_exponent() {
+    let errors = scan_errors("1e");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0].kind, LoxErrorKind::InvalidNumber(s) if s == "1e"));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 2));
+
+    let (tokens, errors) = scan("1e- x");
+    assert_eq!(tokens, vec![t(TokenKind::Identifier("x"), "x", 4), eof(5)]);
+    assert_eq!(errors, 1);
+}
+
+#[test]
+fn scan_number_overflow() {
+    let errors = scan_errors("1e400");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0].kind, LoxErrorKind::InvalidNumber(s) if s == "1e400"));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 5));
+
+    assert_eq!(
+        scan("1e308").0,
+        vec![t(TokenKind::Number(1e308), "1e308", 0), eof(5)]
+    );
+}
+
+#[test]
+fn scan_number_suffix() {
+    let errors = scan_errors("123abc");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        &errors[0].kind,
+        LoxErrorKind::InvalidNumberSuffix { suffix } if *suffix == "abc"
+    ));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 6));
+
+    let errors = scan_errors("1.5_x 0xffz");
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        &errors[1].kind,
+        LoxErrorKind::InvalidNumberSuffix { suffix } if *suffix == "z"
+    ));
+    assert_eq!(errors[1].span.char_range, Span::new(6, 11));
+
+    assert_eq!(
+        scan("123 abc").0,
+        vec![
+            t(TokenKind::Integer(123), "123", 0),
+            t(TokenKind::Identifier("abc"), "abc", 4),
+            eof(7)
+        ]
+    );
+}
+
+#[test]
+fn scan_number_trailing_dot() {
+    // a dot is only taken as a fraction when a digit follows
+    assert_eq!(
+        scan("123.method").0,
+        vec![
+            t(TokenKind::Integer(123), "123", 0),
+            t(TokenKind::Dot, ".", 3),
+            t(TokenKind::Identifier("method"), "method", 4),
+            eof(10)
+        ]
+    );
+    assert_eq!(
+        scan("123.").0,
+        vec![
+            t(TokenKind::Integer(123), "123", 0),
+            t(TokenKind::Dot, ".", 3),
+            eof(4)
+        ]
+    );
+    assert_eq!(
+        scan("123.5").0,
+        vec![t(TokenKind::Number(123.5), "123.5", 0), eof(5)]
+    );
+}
+
+#[test]
+fn scan_dangling_dots() {
+    let dots = |source| {
+        let (tokens, _) = scan(source);
+        dangling_dots(&tokens)
+            .map(|dot| dot.span.char_start())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(dots("123."), vec![3]);
+    assert_eq!(dots("print 1.; 2.5.;"), vec![7, 13]);
+    assert!(dots("123.method").is_empty());
+    assert!(dots("123.5").is_empty());
+    assert!(dots("123 .").is_empty());
+    assert!(dots("a.b").is_empty());
+}
+
+#[test]
+fn scan_number_hex() {
+    assert_eq!(
+        scan("0x10").0,
+        vec![t(TokenKind::Integer(16), "0x10", 0), eof(4)]
+    );
+    assert_eq!(
+        scan("0xff").0,
+        vec![t(TokenKind::Integer(255), "0xff", 0), eof(4)]
+    );
+    assert_eq!(
+        scan("0XaB").0,
+        vec![t(TokenKind::Integer(171), "0XaB", 0), eof(4)]
     );
-    assert_eq!(scan("0.5").0, vec![t(TokenKind::Number(0.5), 0, 2), eof(3)]);
+}
+
+#[test]
+fn scan_number_hex_empty() {
+    let errors = scan_errors("0x");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0].kind, LoxErrorKind::InvalidNumber(s) if s == "0x"));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 2));
+}
+
+#[test]
+fn scan_number_separators() {
+    assert_eq!(
+        scan("1_000_000").0,
+        vec![t(TokenKind::Integer(1000000), "1_000_000", 0), eof(9)]
+    );
+    assert_eq!(
+        scan("1_0.2_5e1_0").0,
+        vec![t(TokenKind::Number(10.25e10), "1_0.2_5e1_0", 0), eof(11)]
+    );
+    // a leading underscore is an identifier, not a number
+    assert_eq!(
+        scan("_5").0,
+        vec![t(TokenKind::Identifier("_5"), "_5", 0), eof(2)]
+    );
+}
+
+#[test]
+fn scan_number_misplaced_separators() {
+    let errors = scan_errors("5_");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0].kind, LoxErrorKind::InvalidNumber(s) if s == "5_"));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 2));
+
+    let errors = scan_errors("1__0");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0].kind, LoxErrorKind::InvalidNumber(s) if s == "1__0"));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 4));
+
+    let errors = scan_errors("1_.5");
+    assert!(matches!(&errors[0].kind, LoxErrorKind::InvalidNumber(s) if s == "1_.5"));
 }
 
 #[test]
 fn scan_string() {
     assert_eq!(
         scan("\"string\"").0,
-        vec![t(TokenKind::String("string"), 0, 7), eof(8)]
+        vec![
+            t(TokenKind::String("string".into()), r#""string""#, 0),
+            eof(8)
+        ]
+    );
+    assert_eq!(
+        scan("\"\"").0,
+        vec![t(TokenKind::String("".into()), r#""""#, 0), eof(2)]
     );
-    assert_eq!(scan("\"\"").0, vec![t(TokenKind::String(""), 0, 1), eof(2)]);
     assert_eq!(
         scan("\"hello world\"").0,
-        vec![t(TokenKind::String("hello world"), 0, 12), eof(13)]
+        vec![
+            t(
+                TokenKind::String("hello world".into()),
+                r#""hello world""#,
+                0
+            ),
+            eof(13)
+        ]
     );
     assert_eq!(
         scan("\"multiple   spaces\"").0,
-        vec![t(TokenKind::String("multiple   spaces"), 0, 18), eof(19)]
+        vec![
+            t(
+                TokenKind::String("multiple   spaces".into()),
+                r#""multiple   spaces""#,
+                0
+            ),
+            eof(19)
+        ]
+    );
+}
+
+#[test]
+fn scan_triple_quoted_string() {
+    let source = "\"\"\"\n  first \"line\"\nsecond\\n\"\"\";";
+    let (tokens, errors) = scan(source);
+    assert_eq!(errors, 0);
+    assert_eq!(
+        tokens[0].kind,
+        TokenKind::String("  first \"line\"\nsecond\\n".into())
+    );
+    assert_eq!(tokens[0].lexeme, &source[..source.len() - 1]);
+    assert_eq!(tokens[1].kind, TokenKind::Semicolon);
+    assert_eq!(tokens[1].span.line, 3);
+
+    assert_eq!(
+        scan(r#""""one line""""#).0[0].kind,
+        TokenKind::String("one line".into())
+    );
+
+    let errors = scan_errors("\"\"\"\nnever closed\"\"");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind, LoxErrorKind::UnterminatedString));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 3));
+}
+
+#[test]
+fn scan_error_after_multiline_string() {
+    let errors = scan_errors("var s = \"a\nb\";\n@");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span.line, 3);
+    assert_eq!(errors[0].span.column, 0);
+
+    let errors = scan_errors("\"\"\"\na\nb\"\"\"; @");
+    assert_eq!(errors[0].span.line, 3);
+}
+
+#[test]
+fn scan_multiline_string_lines() {
+    let (tokens, _) = scan("\"a\nb\"\n@;");
+    // the string is on the line it starts on, like its column
+    assert_eq!(tokens[0].span.line, 1);
+    assert_eq!(tokens[0].span.column, 0);
+    assert_eq!(tokens[1].span.line, 3);
+}
+
+#[test]
+fn scan_raw_string() {
+    assert_eq!(
+        scan(r#""a\nb" r"a\nb""#).0,
+        vec![
+            t(TokenKind::String("a\nb".into()), r#""a\nb""#, 0),
+            t(TokenKind::String(r"a\nb".into()), r#"r"a\nb""#, 7),
+            eof(14)
+        ]
+    );
+    assert_eq!(
+        scan(r#"r"C:\temp\" r"""#).0,
+        vec![
+            t(
+                TokenKind::String(r#"C:\temp\" r"#.into()),
+                r#"r"C:\temp\" r""#,
+                0
+            ),
+            eof(15)
+        ]
+    );
+    // only right before a quote
+    assert_eq!(
+        scan(r#"r "x""#).0,
+        vec![
+            t(TokenKind::Identifier("r"), "r", 0),
+            t(TokenKind::String("x".into()), r#""x""#, 2),
+            eof(5)
+        ]
+    );
+
+    let errors = scan_errors(r#"r"abc\"#);
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind, LoxErrorKind::UnterminatedString));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 2));
+}
+
+#[test]
+fn scan_string_escapes() {
+    assert_eq!(
+        scan(r#""line1\nline2""#).0,
+        vec![
+            t(
+                TokenKind::String("line1\nline2".into()),
+                r#""line1\nline2""#,
+                0
+            ),
+            eof(14)
+        ]
+    );
+    assert_eq!(
+        scan(r#""\t\r\\\"\0""#).0,
+        vec![
+            t(TokenKind::String("\t\r\\\"\0".into()), r#""\t\r\\\"\0""#, 0),
+            eof(12)
+        ]
+    );
+
+    let (tokens, _) = scan(r#""plain""#);
+    assert!(matches!(
+        &tokens[0].kind,
+        TokenKind::String(Cow::Borrowed("plain"))
+    ));
+    let (tokens, _) = scan(r#""esc\n""#);
+    assert!(matches!(&tokens[0].kind, TokenKind::String(Cow::Owned(_))));
+}
+
+#[test]
+fn scan_string_invalid_escape() {
+    let errors = scan_errors(r#""a\qb" x"#);
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::InvalidEscape { c: 'q' }
+    ));
+    assert_eq!(errors[0].span.char_range, Span::new(3, 4));
+
+    // the rest of the string is skipped, scanning resumes after it
+    let (tokens, errors) = scan(r#""a\qb" x"#);
+    assert_eq!(tokens, vec![t(TokenKind::Identifier("x"), "x", 7), eof(8)]);
+    assert_eq!(errors, 1);
+}
+
+#[test]
+fn scan_string_unicode_escapes() {
+    assert_eq!(
+        scan(r#""caf\u{E9}""#).0,
+        vec![
+            t(TokenKind::String("café".into()), r#""caf\u{E9}""#, 0),
+            eof(11)
+        ]
+    );
+    assert_eq!(
+        scan(r#""\u{1F600}!""#).0,
+        vec![
+            t(TokenKind::String("😀!".into()), r#""\u{1F600}!""#, 0),
+            eof(12)
+        ]
+    );
+}
+
+#[test]
+fn scan_string_invalid_unicode_escape() {
+    let errors = scan_errors(r#""\u{D800}""#);
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::InvalidUnicodeEscape { value: "D800" }
+    ));
+    assert_eq!(errors[0].span.char_range, Span::new(1, 9));
+
+    let errors = scan_errors(r#""\u{110000}""#);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::InvalidUnicodeEscape { value: "110000" }
+    ));
+
+    let errors = scan_errors(r#""\u{zz}""#);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::InvalidUnicodeEscape { value: "" }
+    ));
+    assert_eq!(errors[0].span.char_range, Span::new(1, 4));
+}
+
+#[test]
+fn scan_error_line() {
+    let errors = scan_errors("var a;\nvar b;\nvar @;");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span.line, 3);
+    assert!(errors[0].to_string().starts_with("[3:"));
+}
+
+#[test]
+fn scan_error_column() {
+    let errors = scan_errors("var a;\nab @;");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span.line, 2);
+    assert_eq!(errors[0].span.column, 3);
+    // global offset is still there for slicing
+    assert_eq!(errors[0].span.bytes_start(), 10);
+}
+
+#[test]
+fn scan_collect_errors() {
+    let (tokens, errors) = Scanner::scan("x @ \"oops").collect_errors();
+    assert_eq!(tokens, vec![t(TokenKind::Identifier("x"), "x", 0), eof(9)]);
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::UnexpectedCharacter('@')
+    ));
+    assert!(matches!(errors[1].kind, LoxErrorKind::UnterminatedString));
+}
+
+#[test]
+fn scan_error_at_first_byte() {
+    let errors = scan_errors("@ + 1");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::UnexpectedCharacter('@')
+    ));
+    assert_eq!(errors[0].span.line, 1);
+    assert_eq!(errors[0].span.column, 0);
+    assert_eq!(errors[0].span.char_range, Span::new(0, 1));
+    assert_eq!(errors[0].to_string(), "[1:0] Unexpected character '@'");
+
+    // same column at the start of a later line
+    let errors = scan_errors("1;\n@");
+    assert_eq!(errors[0].span.line, 2);
+    assert_eq!(errors[0].span.column, 0);
+    assert_eq!(errors[0].span.char_range, Span::new(3, 4));
+}
+
+#[test]
+fn scan_eof_span() {
+    let (tokens, _) = scan("");
+    assert_eq!(tokens, vec![eof(0)]);
+    assert!(tokens[0].span.char_range.is_empty());
+
+    // no trailing newline, so the end is on the last line
+    let (tokens, _) = scan("a;\nbc");
+    let end = tokens.last().unwrap();
+    assert_eq!(end, &eof_line(2, 2, 5));
+    assert!(end.span.bytes_range.is_empty());
+
+    let (tokens, _) = scan("a;\n");
+    assert_eq!(tokens.last().unwrap(), &eof_line(2, 0, 3));
+}
+
+#[test]
+fn scan_cut_short_is_an_error() {
+    // running out of input mid-token is reported, never taken as a clean end
+    for source in ["\"abc\\", "\"\\u{12", "/* x", "r\"", "\"\"\"", "1e", "0x"] {
+        let (tokens, errors) = Scanner::scan(source).collect_errors();
+        assert_eq!(errors.len(), 1, "{source}");
+        assert!(
+            !matches!(errors[0].kind, LoxErrorKind::UnexpectedEof),
+            "{source}"
+        );
+        assert_eq!(tokens, vec![eof(source.len())], "{source}");
+    }
+
+    // while trailing trivia is just the end
+    assert_eq!(scan("a // done").0.last(), Some(&eof(9)));
+    assert_eq!(scan("a /* done */\n\t").1, 0);
+}
+
+#[test]
+fn scan_size_hint() {
+    let source = "var a = 1;\n// comment\nprint a + \"two\";";
+    let scanner = Scanner::scan(source);
+    let (lower, upper) = scanner.size_hint();
+    let count = scanner.count();
+    assert_eq!(lower, 1);
+    assert!(upper.unwrap() >= count, "{upper:?} < {count}");
+
+    // single char tokens are as dense as it gets
+    let scanner = Scanner::scan("(((@");
+    assert_eq!(scanner.size_hint(), (1, Some(5)));
+    assert_eq!(scanner.count(), 5);
+
+    let mut scanner = Scanner::scan("a");
+    scanner.by_ref().for_each(drop);
+    assert_eq!(scanner.size_hint(), (0, Some(0)));
+}
+
+#[test]
+fn scan_fused() {
+    fn assert_fused<I: std::iter::FusedIterator>(_: &I) {}
+
+    let mut scanner = Scanner::scan("a @");
+    assert_fused(&scanner);
+    assert!(matches!(scanner.next(), Some(Ok(_))));
+    assert!(matches!(scanner.next(), Some(Err(_))));
+    assert_eq!(scanner.next().unwrap().unwrap(), eof(3));
+    for _ in 0..5 {
+        assert!(scanner.next().is_none());
+    }
+}
+
+#[test]
+fn scan_single_eof() {
+    let eofs = |scanner: Scanner<'_>| {
+        scanner
+            .filter(|r| {
+                matches!(
+                    r,
+                    Ok(Token {
+                        kind: TokenKind::Eof,
+                        ..
+                    })
+                )
+            })
+            .count()
+    };
+
+    for source in [
+        "",
+        " \t\n  ",
+        "// only a comment",
+        "/* block */",
+        "#!/bin/lox\n",
+    ] {
+        assert_eq!(eofs(Scanner::scan(source)), 1, "{source:?}");
+        assert_eq!(Scanner::scan(source).count(), 1, "{source:?}");
+    }
+    let (tokens, _) = scan("// only a comment");
+    assert_eq!(tokens, vec![eof(17)]);
+
+    let kept = Scanner::scan("// kept").keep_comments(true);
+    assert_eq!(eofs(kept), 1);
+    let last = Scanner::scan("// kept").keep_comments(true).last();
+    assert_eq!(last.unwrap().unwrap(), eof(7));
+}
+
+#[test]
+fn scan_from_file() {
+    let path = std::env::temp_dir().join(format!("loxide-from-file-{}.lox", std::process::id()));
+    std::fs::write(&path, "print 1;\n@").unwrap();
+    let file = SourceFile::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let (tokens, errors) = Scanner::from_file(&file).collect_errors();
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens[1], t(TokenKind::Integer(1), "1", 6));
+    assert_eq!(errors[0].source.location, file.location.as_deref());
+    assert!(
+        errors[0]
+            .to_string()
+            .ends_with("2:0] Unexpected character '@'")
+    );
+
+    assert!(SourceFile::read("no/such/file.lox").is_err());
+}
+
+#[test]
+fn scan_owned() {
+    fn assert_static<T: 'static>(_: &T) {}
+
+    let scanner = OwnedScanner::from(String::from("var a = \"hi\";"));
+    assert_static(&scanner);
+    let (tokens, errors) = scanner.scan().process_silent();
+    assert_eq!(errors, 0);
+    assert_eq!(tokens, scan("var a = \"hi\";").0);
+    // scanned anew every time
+    assert_eq!(scanner.scan().count(), tokens.len());
+
+    let scanner = OwnedScanner::new("// hi\n");
+    assert_eq!(scanner.file().script, "// hi\n");
+    assert_eq!(
+        scanner
+            .scan()
+            .keep_comments(true)
+            .next()
+            .unwrap()
+            .unwrap()
+            .kind,
+        TokenKind::LineComment(" hi")
+    );
+}
+
+#[test]
+fn scan_from_offset() {
+    let source = "var a = \"é\";\n// note\nprint a + 1.5;\n";
+    let (tokens, _) = scan(source);
+    for (i, token) in tokens.iter().enumerate() {
+        let span = &token.span;
+        let (tail, _) = Scanner::scan_from(source, span.bytes_start(), span.line, span.column)
+            .unwrap()
+            .process_silent();
+        assert_eq!(tail, tokens[i..], "from {i}");
+    }
+
+    // in the middle of `é`
+    let err = Scanner::scan_from(source, 10, 1, 9).err().unwrap();
+    assert!(matches!(
+        err.kind,
+        LoxErrorKind::InvalidOffset { offset: 10 }
+    ));
+    assert!(Scanner::scan_from(source, source.len() + 1, 4, 0).is_err());
+
+    // the start of the script still skips a BOM
+    let (tokens, errors) = Scanner::scan_from("\u{feff}a", 0, 1, 0)
+        .unwrap()
+        .collect_errors();
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(tokens, scan("\u{feff}a").0);
+}
+
+#[test]
+fn scan_all() {
+    assert_eq!(
+        Scanner::scan("a;").scan_all().unwrap(),
+        vec![
+            t(TokenKind::Identifier("a"), "a", 0),
+            t(TokenKind::Semicolon, ";", 1),
+            eof(2)
+        ]
+    );
+
+    let err = Scanner::scan("a @ #").scan_all().unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::UnexpectedCharacter('@')));
+
+    // options still apply
+    let err = Scanner::scan("print a;").budget(5).scan_all().unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::LimitExceeded));
+    let err = Scanner::scan("var café;")
+        .strict_ascii(true)
+        .scan_all()
+        .unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::UnexpectedCharacter('é')));
+}
+
+#[test]
+fn scan_tokens_with_positions() {
+    let positions = Scanner::scan("var a;\n  print a;")
+        .tokens_with_positions()
+        .map(|r| r.map(|(token, position)| (token.kind, position)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let at = |line, col| Position { line, col };
+    assert_eq!(positions[0], (TokenKind::Var, at(1, 0)));
+    assert_eq!(positions[1], (TokenKind::Identifier("a"), at(1, 4)));
+    assert_eq!(positions[3], (TokenKind::Print, at(2, 2)));
+    assert_eq!(positions[4], (TokenKind::Identifier("a"), at(2, 8)));
+    assert_eq!(positions[6], (TokenKind::Eof, at(2, 10)));
+}
+
+#[test]
+fn scan_token_stream() {
+    let mut stream = TokenStream::new(Scanner::scan("a + 1"));
+    let a = t(TokenKind::Identifier("a"), "a", 0);
+
+    assert_eq!(stream.peek().unwrap().as_ref().unwrap(), &a);
+    assert_eq!(stream.peek().unwrap().as_ref().unwrap(), &a);
+    assert_eq!(
+        stream.peek_nth(2).unwrap().as_ref().unwrap(),
+        &t(TokenKind::Integer(1), "1", 4)
     );
+    assert_eq!(stream.next().unwrap().unwrap(), a);
+    assert_eq!(stream.next().unwrap().unwrap(), t(TokenKind::Plus, "+", 2));
+    assert!(stream.peek_nth(2).is_none());
+    assert_eq!(stream.count(), 2);
+}
+
+#[test]
+fn scan_needs_more_input() {
+    assert!(needs_more_input(&scan_errors("print \"hello")));
+    assert!(needs_more_input(&scan_errors("x /* comment")));
+    assert!(!needs_more_input(&scan_errors("print \"hello\";")));
+    assert!(!needs_more_input(&scan_errors("@ \"hello")));
+}
+
+#[test]
+fn scan_lexemes() {
+    let (tokens, _) = scan(r#"while "a\tb" 1_0"#);
+    assert_eq!(tokens[0].lexeme, "while");
+    assert_eq!(tokens[1].lexeme, r#""a\tb""#);
+    assert_eq!(tokens[2].lexeme, "1_0");
+    assert_eq!(tokens[3].lexeme, "");
 }
 
 #[test]
@@ -90,13 +797,13 @@ fn scan_punctuation() {
     assert_eq!(
         scan("( ) { } , . ;").0,
         vec![
-            t(TokenKind::LeftParen, 0, 0),
-            t(TokenKind::RightParen, 2, 2),
-            t(TokenKind::LeftBrace, 4, 4),
-            t(TokenKind::RightBrace, 6, 6),
-            t(TokenKind::Comma, 8, 8),
-            t(TokenKind::Dot, 10, 10),
-            t(TokenKind::Semicolon, 12, 12),
+            t(TokenKind::LeftParen, "(", 0),
+            t(TokenKind::RightParen, ")", 2),
+            t(TokenKind::LeftBrace, "{", 4),
+            t(TokenKind::RightBrace, "}", 6),
+            t(TokenKind::Comma, ",", 8),
+            t(TokenKind::Dot, ".", 10),
+            t(TokenKind::Semicolon, ";", 12),
             eof(13),
         ]
     );
@@ -107,71 +814,211 @@ fn scan_operators() {
     assert_eq!(
         scan("- + * /").0,
         vec![
-            t(TokenKind::Minus, 0, 0),
-            t(TokenKind::Plus, 2, 2),
-            t(TokenKind::Star, 4, 4),
-            t(TokenKind::Slash, 6, 6),
+            t(TokenKind::Minus, "-", 0),
+            t(TokenKind::Plus, "+", 2),
+            t(TokenKind::Star, "*", 4),
+            t(TokenKind::Slash, "/", 6),
             eof(7),
         ]
     );
 }
 
+#[test]
+fn scan_percent() {
+    assert_eq!(
+        scan("10 % 3").0,
+        vec![
+            t(TokenKind::Integer(10), "10", 0),
+            t(TokenKind::Percent, "%", 3),
+            t(TokenKind::Integer(3), "3", 5),
+            eof(6),
+        ]
+    );
+}
+
+#[test]
+fn scan_ternary() {
+    assert_eq!(
+        scan("a ? b : c").0,
+        vec![
+            t(TokenKind::Identifier("a"), "a", 0),
+            t(TokenKind::Question, "?", 2),
+            t(TokenKind::Identifier("b"), "b", 4),
+            t(TokenKind::Colon, ":", 6),
+            t(TokenKind::Identifier("c"), "c", 8),
+            eof(9),
+        ]
+    );
+}
+
 #[test]
 fn scan_two_char_operators() {
     assert_eq!(
         scan("!= == >= <= > <").0,
         vec![
-            t(TokenKind::BangEqual, 0, 1),
-            t(TokenKind::EqualEqual, 3, 4),
-            t(TokenKind::GreaterEqual, 6, 7),
-            t(TokenKind::LessEqual, 9, 10),
-            t(TokenKind::Greater, 12, 12),
-            t(TokenKind::Less, 14, 14),
+            t(TokenKind::BangEqual, "!=", 0),
+            t(TokenKind::EqualEqual, "==", 3),
+            t(TokenKind::GreaterEqual, ">=", 6),
+            t(TokenKind::LessEqual, "<=", 9),
+            t(TokenKind::Greater, ">", 12),
+            t(TokenKind::Less, "<", 14),
             eof(15),
         ]
     );
 }
 
+#[test]
+fn scan_compound_assignment() {
+    assert_eq!(
+        scan("+= -= *= /=").0,
+        vec![
+            t(TokenKind::PlusEqual, "+=", 0),
+            t(TokenKind::MinusEqual, "-=", 3),
+            t(TokenKind::StarEqual, "*=", 6),
+            t(TokenKind::SlashEqual, "/=", 9),
+            eof(11),
+        ]
+    );
+    // `=` has to come right after
+    assert_eq!(
+        scan("+ = -1 *2 /a").0,
+        vec![
+            t(TokenKind::Plus, "+", 0),
+            t(TokenKind::Equal, "=", 2),
+            t(TokenKind::Minus, "-", 4),
+            t(TokenKind::Integer(1), "1", 5),
+            t(TokenKind::Star, "*", 7),
+            t(TokenKind::Integer(2), "2", 8),
+            t(TokenKind::Slash, "/", 10),
+            t(TokenKind::Identifier("a"), "a", 11),
+            eof(12),
+        ]
+    );
+    assert_eq!(
+        scan("a+==b").0,
+        vec![
+            t(TokenKind::Identifier("a"), "a", 0),
+            t(TokenKind::PlusEqual, "+=", 1),
+            t(TokenKind::Equal, "=", 3),
+            t(TokenKind::Identifier("b"), "b", 4),
+            eof(5),
+        ]
+    );
+}
+
 #[test]
 fn scan_keywords() {
-    assert_eq!(scan("and").0, vec![t(TokenKind::And, 0, 2), eof(3)]);
-    assert_eq!(scan("class").0, vec![t(TokenKind::Class, 0, 4), eof(5)]);
-    assert_eq!(scan("else").0, vec![t(TokenKind::Else, 0, 3), eof(4)]);
-    assert_eq!(scan("false").0, vec![t(TokenKind::False, 0, 4), eof(5)]);
-    assert_eq!(scan("fun").0, vec![t(TokenKind::Fun, 0, 2), eof(3)]);
-    assert_eq!(scan("for").0, vec![t(TokenKind::For, 0, 2), eof(3)]);
-    assert_eq!(scan("if").0, vec![t(TokenKind::If, 0, 1), eof(2)]);
-    assert_eq!(scan("nil").0, vec![t(TokenKind::Nil, 0, 2), eof(3)]);
-    assert_eq!(scan("or").0, vec![t(TokenKind::Or, 0, 1), eof(2)]);
-    assert_eq!(scan("print").0, vec![t(TokenKind::Print, 0, 4), eof(5)]);
-    assert_eq!(scan("return").0, vec![t(TokenKind::Return, 0, 5), eof(6)]);
-    assert_eq!(scan("super").0, vec![t(TokenKind::Super, 0, 4), eof(5)]);
-    assert_eq!(scan("this").0, vec![t(TokenKind::This, 0, 3), eof(4)]);
-    assert_eq!(scan("true").0, vec![t(TokenKind::True, 0, 3), eof(4)]);
-    assert_eq!(scan("var").0, vec![t(TokenKind::Var, 0, 2), eof(3)]);
-    assert_eq!(scan("while").0, vec![t(TokenKind::While, 0, 4), eof(5)]);
+    assert_eq!(scan("and").0, vec![t(TokenKind::And, "and", 0), eof(3)]);
+    assert_eq!(
+        scan("class").0,
+        vec![t(TokenKind::Class, "class", 0), eof(5)]
+    );
+    assert_eq!(scan("else").0, vec![t(TokenKind::Else, "else", 0), eof(4)]);
+    assert_eq!(
+        scan("false").0,
+        vec![t(TokenKind::False, "false", 0), eof(5)]
+    );
+    assert_eq!(scan("fun").0, vec![t(TokenKind::Fun, "fun", 0), eof(3)]);
+    assert_eq!(scan("for").0, vec![t(TokenKind::For, "for", 0), eof(3)]);
+    assert_eq!(scan("if").0, vec![t(TokenKind::If, "if", 0), eof(2)]);
+    assert_eq!(scan("nil").0, vec![t(TokenKind::Nil, "nil", 0), eof(3)]);
+    assert_eq!(scan("or").0, vec![t(TokenKind::Or, "or", 0), eof(2)]);
+    assert_eq!(
+        scan("print").0,
+        vec![t(TokenKind::Print, "print", 0), eof(5)]
+    );
+    assert_eq!(
+        scan("return").0,
+        vec![t(TokenKind::Return, "return", 0), eof(6)]
+    );
+    assert_eq!(
+        scan("super").0,
+        vec![t(TokenKind::Super, "super", 0), eof(5)]
+    );
+    assert_eq!(scan("this").0, vec![t(TokenKind::This, "this", 0), eof(4)]);
+    assert_eq!(scan("true").0, vec![t(TokenKind::True, "true", 0), eof(4)]);
+    assert_eq!(scan("var").0, vec![t(TokenKind::Var, "var", 0), eof(3)]);
+    assert_eq!(
+        scan("while").0,
+        vec![t(TokenKind::While, "while", 0), eof(5)]
+    );
 }
 
 #[test]
 fn scan_identifiers() {
     assert_eq!(
         scan("foo").0,
-        vec![t(TokenKind::Identifier("foo"), 0, 2), eof(3)]
+        vec![t(TokenKind::Identifier("foo"), "foo", 0), eof(3)]
     );
     assert_eq!(
         scan("bar123").0,
-        vec![t(TokenKind::Identifier("bar123"), 0, 5), eof(6)]
+        vec![t(TokenKind::Identifier("bar123"), "bar123", 0), eof(6)]
     );
     assert_eq!(
         scan("myVar").0,
-        vec![t(TokenKind::Identifier("myVar"), 0, 4), eof(5)]
+        vec![t(TokenKind::Identifier("myVar"), "myVar", 0), eof(5)]
     );
     assert_eq!(
         scan("CamelCase").0,
-        vec![t(TokenKind::Identifier("CamelCase"), 0, 8), eof(9)]
+        vec![
+            t(TokenKind::Identifier("CamelCase"), "CamelCase", 0),
+            eof(9)
+        ]
+    );
+}
+
+#[test]
+fn scan_identifiers_with_underscores() {
+    assert_eq!(
+        scan("_foo").0,
+        vec![t(TokenKind::Identifier("_foo"), "_foo", 0), eof(4)]
+    );
+    assert_eq!(
+        scan("foo_bar").0,
+        vec![t(TokenKind::Identifier("foo_bar"), "foo_bar", 0), eof(7)]
+    );
+    assert_eq!(
+        scan("__").0,
+        vec![t(TokenKind::Identifier("__"), "__", 0), eof(2)]
     );
 }
 
+#[test]
+fn scan_unicode_identifiers() {
+    let token = |kind, lexeme: &'static str, chars: std::ops::Range<usize>, bytes| Token {
+        kind,
+        lexeme: lexeme.into(),
+        span: SourceSpan {
+            line: 1,
+            column: chars.start,
+            char_range: chars.into(),
+            bytes_range: Span::from(bytes),
+        },
+    };
+
+    assert_eq!(
+        scan("café").0,
+        vec![
+            token(TokenKind::Identifier("café"), "café", 0..4, 0..5),
+            token(TokenKind::Eof, "", 4..4, 5..5),
+        ]
+    );
+    assert_eq!(
+        scan("数").0,
+        vec![
+            token(TokenKind::Identifier("数"), "数", 0..1, 0..3),
+            token(TokenKind::Eof, "", 1..1, 3..3),
+        ]
+    );
+
+    let errors = scan_errors("😀");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::UnexpectedCharacter('😀')
+    ));
+}
+
 #[test]
 fn scan_comments() {
     assert_eq!(scan("// this is a comment").0, vec![eof(20)]);
@@ -179,29 +1026,66 @@ fn scan_comments() {
     assert_eq!(scan("// a").0, vec![eof(4)]);
 }
 
+#[test]
+fn scan_block_comments() {
+    assert_eq!(scan("/* comment */").0, vec![eof(13)]);
+    assert_eq!(scan("/**/").0, vec![eof(4)]);
+    assert_eq!(
+        scan("/* a\nb */ x").0,
+        vec![
+            t_line(TokenKind::Identifier("x"), "x", 2, 5, 10),
+            eof_line(2, 6, 11)
+        ]
+    );
+}
+
+#[test]
+fn scan_nested_block_comments() {
+    assert_eq!(
+        scan("/* outer /* inner */ still in comment */ x").0,
+        vec![t(TokenKind::Identifier("x"), "x", 41), eof(42)]
+    );
+    assert_eq!(
+        scan("/* 1 /* 2 /* 3 */ 2 */ 1 */ x").0,
+        vec![t(TokenKind::Identifier("x"), "x", 28), eof(29)]
+    );
+}
+
+#[test]
+fn scan_unterminated_block_comment() {
+    let errors = scan_errors("x /* outer /* inner */ still in comment");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind, LoxErrorKind::UnterminatedComment));
+    assert_eq!(errors[0].span.char_range, Span::new(2, 4));
+
+    let (tokens, errors) = scan("/* /* */");
+    assert_eq!(tokens, vec![eof(8)]);
+    assert_eq!(errors, 1);
+}
+
 #[test]
 fn scan_whitespace() {
     assert_eq!(scan("   ").0, vec![eof(3)]);
-    assert_eq!(scan("\t\n\r").0, vec![eof_line(1, 3)]);
+    assert_eq!(scan("\t\n\r").0, vec![eof_line(2, 1, 3)]);
 }
 
 #[test]
 fn scan_whitespace_with_tokens() {
     assert_eq!(
         scan(" x ").0,
-        vec![t(TokenKind::Identifier("x"), 1, 1), eof(3)]
+        vec![t(TokenKind::Identifier("x"), "x", 1), eof(3)]
     );
-    assert_eq!(scan("\t+\t").0, vec![t(TokenKind::Plus, 1, 1), eof(3)]);
+    assert_eq!(scan("\t+\t").0, vec![t(TokenKind::Plus, "+", 1), eof(3)]);
     assert_eq!(
         scan("  42  ").0,
-        vec![t(TokenKind::Number(42.0), 2, 3), eof(6)]
+        vec![t(TokenKind::Integer(42), "42", 2), eof(6)]
     );
     assert_eq!(
         scan("a\nb").0,
         vec![
-            t_line(TokenKind::Identifier("a"), 0, 0, 0),
-            t_line(TokenKind::Identifier("b"), 1, 2, 2),
-            eof_line(1, 3),
+            t_line(TokenKind::Identifier("a"), "a", 1, 0, 0),
+            t_line(TokenKind::Identifier("b"), "b", 2, 0, 2),
+            eof_line(2, 1, 3),
         ]
     );
 }
@@ -211,24 +1095,551 @@ fn scan_mixed_tokens() {
     let input = r#"var x = 10; if (x != 5) { print "hello" + "world"; } // test!"#;
     let tokens = scan(input).0;
 
-    assert_eq!(tokens[0], t(TokenKind::Var, 0, 2));
-    assert_eq!(tokens[1], t(TokenKind::Identifier("x"), 4, 4));
-    assert_eq!(tokens[2], t(TokenKind::Equal, 6, 6));
-    assert_eq!(tokens[3], t(TokenKind::Number(10.0), 8, 9));
-    assert_eq!(tokens[4], t(TokenKind::Semicolon, 10, 10));
-    assert_eq!(tokens[5], t(TokenKind::If, 12, 13));
-    assert_eq!(tokens[6], t(TokenKind::LeftParen, 15, 15));
-    assert_eq!(tokens[7], t(TokenKind::Identifier("x"), 16, 16));
-    assert_eq!(tokens[8], t(TokenKind::BangEqual, 18, 19));
-    assert_eq!(tokens[9], t(TokenKind::Number(5.0), 21, 21));
-    assert_eq!(tokens[10], t(TokenKind::RightParen, 22, 22));
-    assert_eq!(tokens[11], t(TokenKind::LeftBrace, 24, 24));
-    assert_eq!(tokens[12], t(TokenKind::Print, 26, 30));
-    assert_eq!(tokens[13], t(TokenKind::String("hello"), 32, 38));
-    assert_eq!(tokens[14], t(TokenKind::Plus, 40, 40));
-    assert_eq!(tokens[15], t(TokenKind::String("world"), 42, 48));
-    assert_eq!(tokens[16], t(TokenKind::Semicolon, 49, 49));
-    assert_eq!(tokens[17], t(TokenKind::RightBrace, 51, 51));
+    assert_eq!(tokens[0], t(TokenKind::Var, "var", 0));
+    assert_eq!(tokens[1], t(TokenKind::Identifier("x"), "x", 4));
+    assert_eq!(tokens[2], t(TokenKind::Equal, "=", 6));
+    assert_eq!(tokens[3], t(TokenKind::Integer(10), "10", 8));
+    assert_eq!(tokens[4], t(TokenKind::Semicolon, ";", 10));
+    assert_eq!(tokens[5], t(TokenKind::If, "if", 12));
+    assert_eq!(tokens[6], t(TokenKind::LeftParen, "(", 15));
+    assert_eq!(tokens[7], t(TokenKind::Identifier("x"), "x", 16));
+    assert_eq!(tokens[8], t(TokenKind::BangEqual, "!=", 18));
+    assert_eq!(tokens[9], t(TokenKind::Integer(5), "5", 21));
+    assert_eq!(tokens[10], t(TokenKind::RightParen, ")", 22));
+    assert_eq!(tokens[11], t(TokenKind::LeftBrace, "{", 24));
+    assert_eq!(tokens[12], t(TokenKind::Print, "print", 26));
+    assert_eq!(
+        tokens[13],
+        t(TokenKind::String("hello".into()), r#""hello""#, 32)
+    );
+    assert_eq!(tokens[14], t(TokenKind::Plus, "+", 40));
+    assert_eq!(
+        tokens[15],
+        t(TokenKind::String("world".into()), r#""world""#, 42)
+    );
+    assert_eq!(tokens[16], t(TokenKind::Semicolon, ";", 49));
+    assert_eq!(tokens[17], t(TokenKind::RightBrace, "}", 51));
     assert_eq!(tokens[18], eof(61));
     assert_eq!(tokens.len(), 19);
 }
+
+#[test]
+fn scan_large_source() {
+    let source = "var x = 1.5 + \"str\"; // comment\n".repeat(50_000);
+    let (tokens, errors) = scan(&source);
+    assert_eq!(errors, 0);
+    assert_eq!(tokens.len(), 7 * 50_000 + 1);
+    assert_eq!(tokens.last().unwrap().span.line, 50_001);
+}
+
+#[test]
+fn scan_long_trivia() {
+    let source = format!("{}x", "\n".repeat(100_000));
+    assert_eq!(
+        scan(&source).0,
+        vec![
+            t_line(TokenKind::Identifier("x"), "x", 100_001, 0, 100_000),
+            eof_line(100_001, 1, 100_001)
+        ]
+    );
+
+    let source = "// comment\n/* block */\n".repeat(100_000);
+    let (tokens, errors) = scan(&source);
+    assert_eq!(errors, 0);
+    assert_eq!(tokens, vec![eof_line(200_001, 0, source.len())]);
+}
+
+#[test]
+fn scan_unterminated_string_span() {
+    let errors = scan_errors("var a;\nvar b = \"open\nstill open");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind, LoxErrorKind::UnterminatedString));
+    assert_eq!(errors[0].span.line, 2);
+    assert_eq!(errors[0].span.column, 8);
+    assert_eq!(errors[0].span.char_range, Span::new(15, 16));
+}
+
+#[test]
+fn scan_crlf_lines() {
+    assert_eq!(
+        scan("var a;\r\n  a = 1;\r\n").0,
+        vec![
+            t_line(TokenKind::Var, "var", 1, 0, 0),
+            t_line(TokenKind::Identifier("a"), "a", 1, 4, 4),
+            t_line(TokenKind::Semicolon, ";", 1, 5, 5),
+            t_line(TokenKind::Identifier("a"), "a", 2, 2, 10),
+            t_line(TokenKind::Equal, "=", 2, 4, 12),
+            t_line(TokenKind::Integer(1), "1", 2, 6, 14),
+            t_line(TokenKind::Semicolon, ";", 2, 7, 15),
+            eof_line(3, 0, 18),
+        ]
+    );
+}
+
+#[test]
+fn scan_shebang() {
+    assert_eq!(
+        scan("#!/usr/bin/env loxide\nprint 1;").0,
+        vec![
+            t_line(TokenKind::Print, "print", 2, 0, 22),
+            t_line(TokenKind::Integer(1), "1", 2, 6, 28),
+            t_line(TokenKind::Semicolon, ";", 2, 7, 29),
+            eof_line(2, 8, 30),
+        ]
+    );
+
+    let errors = scan_errors("print 1;\n#!no");
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::UnexpectedCharacter('#')
+    ));
+}
+
+#[test]
+fn scan_bom() {
+    let (plain, _) = scan("print 1;");
+    let (bom, errors) = scan("\u{FEFF}print 1;");
+    assert_eq!(errors, 0);
+    assert_eq!(bom.len(), plain.len());
+    for (bom, plain) in bom.iter().zip(&plain) {
+        assert_eq!(bom.kind, plain.kind);
+        assert_eq!(bom.lexeme, plain.lexeme);
+        assert_eq!(bom.span.column, plain.span.column);
+        assert_eq!(bom.span.bytes_start(), plain.span.bytes_start() + 3);
+    }
+
+    let (tokens, _) = scan("\u{FEFF}#!/usr/bin/env loxide\nx");
+    assert_eq!(tokens[0].kind, TokenKind::Identifier("x"));
+}
+
+#[test]
+fn scan_kept_comments() {
+    let source = "x; // note\n/* a /* b */ */y";
+    let tokens: Vec<_> = Scanner::scan(source).keep_comments(true).process_silent().0;
+    assert_eq!(
+        tokens,
+        vec![
+            t(TokenKind::Identifier("x"), "x", 0),
+            t(TokenKind::Semicolon, ";", 1),
+            t(TokenKind::LineComment(" note"), "// note", 3),
+            t_line(
+                TokenKind::BlockComment(" a /* b */ "),
+                "/* a /* b */ */",
+                2,
+                0,
+                11
+            ),
+            t_line(TokenKind::Identifier("y"), "y", 2, 15, 26),
+            eof_line(2, 16, 27),
+        ]
+    );
+
+    assert_eq!(
+        scan(source).0,
+        vec![
+            t(TokenKind::Identifier("x"), "x", 0),
+            t(TokenKind::Semicolon, ";", 1),
+            t_line(TokenKind::Identifier("y"), "y", 2, 15, 26),
+            eof_line(2, 16, 27),
+        ]
+    );
+}
+
+#[test]
+fn case_insensitive_keywords() {
+    let kinds = |scanner: Scanner<'static>| {
+        scanner
+            .process_silent()
+            .0
+            .into_iter()
+            .map(|t| t.kind)
+            .collect::<Vec<_>>()
+    };
+
+    let source = "PRINT Print myVar;";
+    assert_eq!(
+        kinds(Scanner::scan(source).case_insensitive_keywords(true)),
+        [
+            TokenKind::Print,
+            TokenKind::Print,
+            TokenKind::Identifier("myVar"),
+            TokenKind::Semicolon,
+            TokenKind::Eof,
+        ]
+    );
+    assert_eq!(
+        kinds(Scanner::scan(source)),
+        [
+            TokenKind::Identifier("PRINT"),
+            TokenKind::Identifier("Print"),
+            TokenKind::Identifier("myVar"),
+            TokenKind::Semicolon,
+            TokenKind::Eof,
+        ]
+    );
+
+    let scanner = OwnedScanner::new("WHILE");
+    let token = scanner
+        .scan()
+        .case_insensitive_keywords(true)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(token.kind, TokenKind::While);
+    assert_eq!(token.lexeme, "WHILE");
+}
+
+#[test]
+fn keyword_overrides() {
+    use std::collections::HashMap;
+
+    let keywords = HashMap::from([("func", TokenKind::Fun), ("let", TokenKind::Var)]);
+    let (tokens, errors) = Scanner::scan("func f let fun")
+        .keywords(&keywords)
+        .unwrap()
+        .process_silent();
+    assert_eq!(errors, 0);
+    let kinds = tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Fun,
+            TokenKind::Identifier("f"),
+            TokenKind::Var,
+            TokenKind::Fun,
+            TokenKind::Eof,
+        ]
+    );
+
+    let keywords = HashMap::from([("one", TokenKind::Number(1.0))]);
+    let err = Scanner::scan("one").keywords(&keywords).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        LoxErrorKind::InvalidKeyword {
+            word: "one",
+            kind: "number"
+        }
+    ));
+}
+
+#[test]
+fn warnings() {
+    let mut scanner = Scanner::scan("var a = 007 + 0 + 0.5 + 10;");
+    let (tokens, errors) = scanner.by_ref().process_silent();
+    assert_eq!(errors, 0);
+    assert_eq!(tokens[3], t(TokenKind::Integer(7), "007", 8));
+
+    let warnings = scanner.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(
+        warnings[0].kind,
+        LoxWarningKind::LeadingZero { number: "007" }
+    ));
+    assert_eq!(
+        warnings[0].to_string(),
+        "[1:8..10] Redundant leading zero in '007'"
+    );
+    assert!(scanner.take_warnings().is_empty());
+
+    let long = "a".repeat(65);
+    let mut scanner = Scanner::scan(long.as_str());
+    assert_eq!(scanner.by_ref().count(), 2);
+    let warnings = scanner.take_warnings();
+    assert!(matches!(
+        warnings[..],
+        [LoxWarning {
+            kind: LoxWarningKind::LongIdentifier { len: 65 },
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn strict_ascii() {
+    let strict = |source| Scanner::scan(source).strict_ascii(true).collect_errors();
+
+    let (_, errors) = strict("var café = 1;");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].kind,
+        LoxErrorKind::UnexpectedCharacter('é')
+    ));
+    assert_eq!(errors[0].span.column, 7);
+
+    let (_, errors) = strict("var π = 3;");
+    assert!(matches!(
+        errors[..],
+        [LoxError {
+            kind: LoxErrorKind::UnexpectedCharacter('π'),
+            ..
+        }]
+    ));
+
+    let (tokens, errors) = strict("print \"café ☕\"; // é\n/* π */");
+    assert!(errors.is_empty());
+    assert_eq!(tokens[1].kind, TokenKind::String("café ☕".into()));
+
+    assert_eq!(scan("var café = 1;").1, 0);
+
+    let (tokens, errors) = strict("1é");
+    assert_eq!(tokens[0].kind, TokenKind::Integer(1));
+    assert!(matches!(
+        errors[..],
+        [LoxError {
+            kind: LoxErrorKind::UnexpectedCharacter('é'),
+            ..
+        }]
+    ));
+    assert!(matches!(
+        scan_errors("1é")[..],
+        [LoxError {
+            kind: LoxErrorKind::InvalidNumberSuffix { suffix: "é" },
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn exact_spans() {
+    let span = |column: usize, chars: (usize, usize), bytes: (usize, usize)| SourceSpan {
+        line: 1,
+        column,
+        char_range: Span::new(chars.0, chars.1),
+        bytes_range: Span::new(bytes.0, bytes.1),
+    };
+
+    let (tokens, errors) = scan("while + 12.5 \"é\" x");
+    assert_eq!(errors, 0);
+    let spans = tokens.iter().map(|t| t.span.clone()).collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        [
+            span(0, (0, 5), (0, 5)),
+            span(6, (6, 7), (6, 7)),
+            span(8, (8, 12), (8, 12)),
+            span(13, (13, 16), (13, 17)),
+            span(17, (17, 18), (18, 19)),
+            span(18, (18, 18), (19, 19)),
+        ]
+    );
+    for token in &tokens {
+        assert_eq!(
+            token.lexeme,
+            &"while + 12.5 \"é\" x"[token.span.bytes_start()..token.span.bytes_end()]
+        );
+    }
+}
+
+#[test]
+fn loop_control() {
+    assert_eq!(
+        scan("break continue breaker continued").0,
+        vec![
+            t(TokenKind::Break, "break", 0),
+            t(TokenKind::Continue, "continue", 6),
+            t(TokenKind::Identifier("breaker"), "breaker", 15),
+            t(TokenKind::Identifier("continued"), "continued", 23),
+            eof(32),
+        ]
+    );
+}
+
+#[test]
+fn fat_arrow() {
+    assert_eq!(
+        scan("=> == = =>= ===>").0,
+        vec![
+            t(TokenKind::FatArrow, "=>", 0),
+            t(TokenKind::EqualEqual, "==", 3),
+            t(TokenKind::Equal, "=", 6),
+            t(TokenKind::FatArrow, "=>", 8),
+            t(TokenKind::Equal, "=", 10),
+            t(TokenKind::EqualEqual, "==", 12),
+            t(TokenKind::FatArrow, "=>", 14),
+            eof(16),
+        ]
+    );
+    assert_eq!(
+        scan("= >").0,
+        vec![
+            t(TokenKind::Equal, "=", 0),
+            t(TokenKind::Greater, ">", 2),
+            eof(3),
+        ]
+    );
+}
+
+#[test]
+fn one_shot_scan() {
+    let (tokens, errors) = loxide::scan_str("print 1 + @;");
+    let kinds = tokens.iter().map(|t| &t.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            &TokenKind::Print,
+            &TokenKind::Integer(1),
+            &TokenKind::Plus,
+            &TokenKind::Semicolon,
+            &TokenKind::Eof,
+        ]
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].source.location, None);
+    assert_eq!(errors[0].to_string(), "[1:10] Unexpected character '@'");
+}
+
+#[test]
+fn adjacent_tokens() {
+    assert_eq!(
+        scan("1+2").0,
+        vec![
+            t(TokenKind::Integer(1), "1", 0),
+            t(TokenKind::Plus, "+", 1),
+            t(TokenKind::Integer(2), "2", 2),
+            eof(3),
+        ]
+    );
+    assert_eq!(
+        scan("\"a\"+\"b\"").0,
+        vec![
+            t(TokenKind::String("a".into()), "\"a\"", 0),
+            t(TokenKind::Plus, "+", 3),
+            t(TokenKind::String("b".into()), "\"b\"", 4),
+            eof(7),
+        ]
+    );
+    assert_eq!(
+        scan("x+y").0,
+        vec![
+            t(TokenKind::Identifier("x"), "x", 0),
+            t(TokenKind::Plus, "+", 1),
+            t(TokenKind::Identifier("y"), "y", 2),
+            eof(3),
+        ]
+    );
+    // nothing dropped or doubled right after a string either
+    assert_eq!(
+        scan("\"a\"\"b\";").0,
+        vec![
+            t(TokenKind::String("a".into()), "\"a\"", 0),
+            t(TokenKind::String("b".into()), "\"b\"", 3),
+            t(TokenKind::Semicolon, ";", 6),
+            eof(7),
+        ]
+    );
+}
+
+#[test]
+fn empty_string_boundaries() {
+    let (tokens, errors) = scan("\"\"+\"\"");
+    assert_eq!(errors, 0);
+    assert_eq!(
+        tokens,
+        vec![
+            t(TokenKind::String("".into()), "\"\"", 0),
+            t(TokenKind::Plus, "+", 2),
+            t(TokenKind::String("".into()), "\"\"", 3),
+            eof(5),
+        ]
+    );
+    assert_eq!(tokens[0].span.bytes_range, Span::new(0, 2));
+    assert_eq!(tokens[2].span.bytes_range, Span::new(3, 5));
+}
+
+#[test]
+fn print_empty_string() {
+    assert_eq!(
+        scan("print \"\";").0,
+        vec![
+            t(TokenKind::Print, "print", 0),
+            t(TokenKind::String("".into()), "\"\"", 6),
+            t(TokenKind::Semicolon, ";", 8),
+            eof(9),
+        ]
+    );
+}
+
+#[test]
+fn length_limits() {
+    let long = "a".repeat(1000);
+    let (tokens, errors) = Scanner::scan(long.as_str())
+        .max_identifier_len(255)
+        .collect_errors();
+    assert_eq!(tokens, vec![eof(1000)]);
+    assert!(matches!(
+        errors[..],
+        [LoxError {
+            kind: LoxErrorKind::IdentifierTooLong {
+                len: 1000,
+                max: 255
+            },
+            ..
+        }]
+    ));
+    assert_eq!(errors[0].span.char_range, Span::new(0, 1000));
+
+    let (tokens, errors) = Scanner::scan(long.as_str()).collect_errors();
+    assert!(errors.is_empty());
+    assert_eq!(tokens[0].kind, TokenKind::Identifier(&long));
+
+    let source = "\"abcd\" \"abc\" r\"a\\nbc\" \"\"\"abcde\"\"\" \"\\n\\n\\n\"";
+    let (tokens, errors) = Scanner::scan(source).max_string_len(3).collect_errors();
+    let lens = errors
+        .iter()
+        .map(|err| match err.kind {
+            LoxErrorKind::StringTooLong { len, max: 3 } => len,
+            _ => panic!("{err}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(lens, [4, 5, 5]);
+    // escapes count as what they stand for
+    let strings = tokens.iter().map(|t| t.lexeme.as_ref()).collect::<Vec<_>>();
+    assert_eq!(strings, ["\"abc\"", "\"\\n\\n\\n\"", ""]);
+}
+
+#[test]
+fn budget() {
+    let mut scanner = Scanner::scan("print 1 + 2;").budget(5);
+    assert_eq!(
+        scanner.next().unwrap().unwrap(),
+        t(TokenKind::Print, "print", 0)
+    );
+    let err = scanner.next().unwrap().unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::LimitExceeded));
+    assert_eq!(err.span.column, 5);
+    // no `Eof`, the scan is over
+    assert!(scanner.next().is_none());
+
+    let (tokens, errors) = Scanner::scan("print 1 + 2;").budget(12).process_silent();
+    assert_eq!((tokens.len(), errors), (6, 0));
+
+    // not an `=` when the second char is over budget
+    let mut scanner = Scanner::scan("==").budget(1);
+    let err = scanner.next().unwrap().unwrap_err();
+    assert!(matches!(err.kind, LoxErrorKind::LimitExceeded));
+    assert!(scanner.next().is_none());
+}
+
+#[test]
+fn stream_tokens() {
+    let source = "var a = 1;\nprint a + \"é\";\n".repeat(300);
+
+    let mut streamed = Vec::new();
+    let errors = print_tokens_streaming(
+        &mut Scanner::scan(source.as_str()),
+        &mut streamed,
+        usize::MAX,
+        &RenderOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(errors, 0);
+
+    let mut collected = Vec::new();
+    for (i, token) in scan(&source).0.iter().enumerate() {
+        write_token(&mut collected, i, token).unwrap();
+    }
+    assert_eq!(
+        String::from_utf8(streamed).unwrap(),
+        String::from_utf8(collected).unwrap()
+    );
+}
@@ -0,0 +1,133 @@
+use loxide::source::{LineIndex, SourceSpan, Span};
+
+#[test]
+fn line_index_lookups() {
+    let index = LineIndex::new("var a;\r\nprint é;\nlast");
+    assert_eq!(index.line_count(), 3);
+    assert_eq!(index.line_text(1), "var a;");
+    assert_eq!(index.line_text(2), "print é;");
+    assert_eq!(index.line_text(3), "last");
+    assert_eq!(index.line_text(0), "");
+    assert_eq!(index.line_text(4), "");
+
+    assert_eq!(index.line_col(0), (1, 0));
+    assert_eq!(index.line_col(4), (1, 4));
+    // `;` after the two-byte `é`
+    assert_eq!(index.line_col(16), (2, 7));
+    assert_eq!(index.line_col(18), (3, 0));
+    assert_eq!(index.line_col(21), (3, 3));
+}
+
+#[test]
+fn line_index_trailing_newline() {
+    let index = LineIndex::new("a\n");
+    assert_eq!(index.line_count(), 2);
+    assert_eq!(index.line_text(1), "a");
+    assert_eq!(index.line_text(2), "");
+    assert_eq!(index.line_col(2), (2, 0));
+}
+
+#[cfg(feature = "graphemes")]
+#[test]
+fn line_index_grapheme_columns() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let source = "\"👨‍👩‍👧\" @";
+    let (_, errors) = Scanner::scan(source).collect_errors();
+    let offset = errors[0].span.bytes_start();
+    assert_eq!(errors[0].span.column, 8);
+
+    let index = LineIndex::new(source);
+    assert_eq!(index.line_col(offset), (1, 8));
+    assert_eq!(index.grapheme_col(offset), (1, 4));
+}
+
+#[test]
+fn span_len_and_contains() {
+    let span = Span::new(2, 5);
+    assert_eq!(span.len(), 3);
+    assert!(!span.is_empty());
+    assert!(span.contains(2));
+    assert!(span.contains(4));
+    assert!(!span.contains(5));
+
+    assert_eq!(Span::new(3, 3).len(), 0);
+    assert!(Span::new(3, 3).is_empty());
+    assert!(!Span::new(3, 3).contains(3));
+}
+
+#[test]
+fn span_merge() {
+    let a = Span::new(2, 5);
+    let b = Span::new(4, 9);
+    assert_eq!(a.merge(b), Span::new(2, 9));
+    assert_eq!(b.merge(a), Span::new(2, 9));
+    // a gap in between is covered too
+    assert_eq!(Span::new(0, 1).merge(Span::new(7, 8)), Span::new(0, 8));
+}
+
+#[test]
+fn span_from_ranges() {
+    assert_eq!(Span::from(2..=4), Span::new(2, 5));
+    assert_eq!(Span::from(2..5), Span::new(2, 5));
+    assert_eq!(std::ops::Range::from(Span::new(2, 5)), 2..5);
+}
+
+#[test]
+fn equivalent_paths_share_a_location() {
+    use loxide::{Scanner, source::normalize_location};
+    use std::path::Path;
+
+    let plain = normalize_location(Path::new("Cargo.toml"));
+    let roundabout = normalize_location(Path::new("./src/../Cargo.toml"));
+    let absolute = normalize_location(&Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"));
+    assert_eq!(plain, roundabout);
+    assert_eq!(plain, absolute);
+
+    let err = |location: &Path| {
+        let source = ("@", location);
+        Scanner::scan(source)
+            .find_map(Result::err)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(err(&plain), err(&roundabout));
+    assert_eq!(err(&plain), "[Cargo.toml 1:0] Unexpected character '@'");
+
+    // nothing to resolve, but no panic either
+    let missing = Path::new("./no/such/file.lox");
+    assert_eq!(normalize_location(missing), missing);
+}
+
+#[test]
+fn windows_locations_use_slashes() {
+    use loxide::source::slash_windows_path;
+
+    assert_eq!(slash_windows_path(r"\\?\C:\lox\a.lox"), "C:/lox/a.lox");
+    assert_eq!(
+        slash_windows_path(r"\\?\UNC\host\share\a.lox"),
+        "//host/share/a.lox"
+    );
+    assert_eq!(slash_windows_path(r"lox\a.lox"), "lox/a.lox");
+    assert_eq!(slash_windows_path("lox/a.lox"), "lox/a.lox");
+}
+
+#[test]
+fn span_display() {
+    assert_eq!(Span::new(4, 5).to_string(), "4");
+    assert_eq!(Span::new(4, 7).to_string(), "4..7");
+    assert_eq!(Span::new(4, 4).to_string(), "4..4");
+}
+
+#[test]
+fn span_end_position() {
+    let span = |column: usize, len: usize| SourceSpan {
+        line: 2,
+        column,
+        char_range: Span::new(10, 10 + len),
+        bytes_range: Span::new(10, 10 + len),
+    };
+    assert_eq!(span(4, 3).end_position("abc"), (2, 7));
+    assert_eq!(span(4, 6).end_position("\"a\nbc\""), (3, 3));
+    assert_eq!(span(0, 8).end_position("/*\n\n*/"), (4, 2));
+}
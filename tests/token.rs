@@ -0,0 +1,428 @@
+use loxide::{
+    source::{SourceSpan, Span},
+    token::{
+        OwnedToken, OwnedTokenKind, Token, TokenKind, color_for, count_kinds, format_number,
+        group_by_line, render_source,
+    },
+};
+
+fn t(kind: TokenKind<'_>, start: usize, end: usize) -> Token<'_> {
+    Token {
+        kind,
+        lexeme: "".into(),
+        span: SourceSpan {
+            line: 1,
+            column: start,
+            char_range: Span::new(start, end),
+            bytes_range: Span::new(start, end),
+        },
+    }
+}
+
+#[test]
+fn clone_token() {
+    let token = t(TokenKind::Number(4.2), 0, 2);
+    assert_eq!(token.clone(), token);
+
+    let token = t(TokenKind::String("hi".into()), 0, 3);
+    assert_eq!(token.clone(), token);
+}
+
+#[test]
+fn owned_token() {
+    fn assert_send_static<T: Send + 'static>(_: &T) {}
+
+    let source = String::from("\"hi\"");
+    let owned: OwnedToken = Token {
+        lexeme: source.as_str().into(),
+        ..t(TokenKind::String("hi".into()), 0, 4)
+    }
+    .to_owned();
+    drop(source);
+
+    assert_send_static(&owned);
+    assert_eq!(owned.kind, OwnedTokenKind::String("hi".to_string()));
+    assert_eq!(owned.lexeme, "\"hi\"");
+    let owned = std::thread::spawn(move || owned).join().unwrap();
+    assert_eq!(
+        owned.as_token(),
+        Token {
+            lexeme: "\"hi\"".into(),
+            ..t(TokenKind::String("hi".into()), 0, 4)
+        }
+    );
+
+    let ident = t(TokenKind::Identifier("x"), 0, 1);
+    assert_eq!(ident.to_owned().as_token(), ident);
+    let semicolon = t(TokenKind::Semicolon, 0, 1);
+    assert_eq!(semicolon.to_owned().kind, OwnedTokenKind::Semicolon);
+}
+
+#[test]
+fn display_spans() {
+    assert_eq!(
+        t(TokenKind::Semicolon, 3, 4).to_string(),
+        "Token::Semicolon@3"
+    );
+    assert_eq!(
+        t(TokenKind::Identifier("abc"), 3, 6).to_string(),
+        "Token::Identifier(\"abc\")@3..6"
+    );
+    assert_eq!(t(TokenKind::Eof, 0, 0).to_string(), "Token::Eof@0..0");
+    assert_eq!(t(TokenKind::Eof, 9, 9).to_string(), "Token::Eof@9..9");
+}
+
+#[test]
+fn token_colors() {
+    assert_ne!(
+        color_for(&TokenKind::While),
+        color_for(&TokenKind::Number(1.0))
+    );
+    assert_eq!(color_for(&TokenKind::While), color_for(&TokenKind::Var));
+    assert_eq!(
+        color_for(&TokenKind::Integer(1)),
+        color_for(&TokenKind::String("".into()))
+    );
+    assert_ne!(
+        color_for(&TokenKind::Identifier("x")),
+        color_for(&TokenKind::Plus)
+    );
+    // punctuation outside the groups is colored like operators
+    assert_eq!(
+        color_for(&TokenKind::Semicolon),
+        color_for(&TokenKind::LeftParen)
+    );
+    assert_eq!(
+        color_for(&TokenKind::LineComment("")),
+        color_for(&TokenKind::Eof)
+    );
+}
+
+#[test]
+fn group_tokens_by_line() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let (tokens, _) = Scanner::scan("var a = 1;\n\nprint \"a\nb\" + a;\nprint a;").process_silent();
+    let groups = group_by_line(&tokens)
+        .into_iter()
+        .map(|(line, group)| {
+            let kinds = group
+                .iter()
+                .map(|t| t.kind.lexeme_kind_name())
+                .collect::<Vec<_>>();
+            (line, kinds)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        groups,
+        vec![
+            (
+                1,
+                vec!["var", "identifier", "equal", "integer", "semicolon"]
+            ),
+            // a string over two lines belongs to the line it starts on
+            (3, vec!["print", "string"]),
+            (4, vec!["plus", "identifier", "semicolon"]),
+            (5, vec!["print", "identifier", "semicolon", "eof"]),
+        ]
+    );
+}
+
+#[test]
+fn format_numbers() {
+    assert_eq!(format_number(3.0), "3");
+    assert_eq!(format_number(-12.0), "-12");
+    assert_eq!(format_number(0.1), "0.1");
+    assert_eq!(format_number(-0.5), "-0.5");
+    // a fraction smaller than `f64::EPSILON` is still a fraction
+    assert_eq!(format_number(1e-17), "0.00000000000000001");
+    // shortest form that parses back, not all the digits of the binary value
+    assert_eq!(format_number(0.1 + 0.2), "0.30000000000000004");
+    assert_eq!(format!("{:.20}", 0.1 + 0.2), "0.30000000000000004441");
+}
+
+#[test]
+fn keywords() {
+    assert_eq!(TokenKind::keyword("for"), Some(TokenKind::For));
+    assert_eq!(TokenKind::keyword("fore"), None);
+    assert_eq!(TokenKind::from("while"), TokenKind::While);
+    assert_eq!(TokenKind::from("fore"), TokenKind::Identifier("fore"));
+
+    assert_eq!("for".parse::<OwnedTokenKind>(), Ok(OwnedTokenKind::For));
+    assert_eq!(
+        "fore".parse::<OwnedTokenKind>(),
+        Ok(OwnedTokenKind::Identifier("fore".to_string()))
+    );
+}
+
+#[test]
+fn loop_control_keywords() {
+    assert_eq!(TokenKind::from("break"), TokenKind::Break);
+    assert_eq!(TokenKind::from("continue"), TokenKind::Continue);
+    assert_eq!(TokenKind::Break.lexeme_kind_name(), "break");
+    assert_eq!(TokenKind::Continue.to_string(), "continue");
+}
+
+#[test]
+fn count_token_kinds() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let (tokens, _) = Scanner::scan("var a = 1;\nvar b = a + 2;\nprint a + b;").process_silent();
+    assert_eq!(
+        count_kinds(&tokens),
+        vec![
+            ("identifier", 5),
+            ("semicolon", 3),
+            ("equal", 2),
+            ("integer", 2),
+            ("plus", 2),
+            ("var", 2),
+            ("print", 1),
+        ]
+    );
+    assert!(count_kinds(&Scanner::scan("").process_silent().0).is_empty());
+}
+
+#[test]
+fn kind_names() {
+    assert_eq!(TokenKind::LeftParen.lexeme_kind_name(), "left_paren");
+    assert_eq!(TokenKind::BangEqual.lexeme_kind_name(), "bang_equal");
+    assert_eq!(TokenKind::Number(1.5).lexeme_kind_name(), "number");
+    assert_eq!(TokenKind::Integer(1).lexeme_kind_name(), "integer");
+    assert_eq!(TokenKind::Identifier("x").lexeme_kind_name(), "identifier");
+    assert_eq!(TokenKind::String("".into()).lexeme_kind_name(), "string");
+    assert_eq!(TokenKind::While.lexeme_kind_name(), "while");
+    assert_eq!(TokenKind::Eof.lexeme_kind_name(), "eof");
+}
+
+#[test]
+fn lexeme_uses_byte_span() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let source = "\"é\" + 12";
+    let (tokens, _) = Scanner::scan(source).process_silent();
+    let number = &tokens[2];
+    assert_eq!(number.span.char_range, Span::new(6, 8));
+    assert_eq!(number.span.bytes_range, Span::new(7, 9));
+    assert_eq!(number.lexeme_in(source), "12");
+    assert_eq!(tokens[0].lexeme_in(source), "\"é\"");
+    assert_eq!(number.lexeme, number.lexeme_in(source));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let (tokens, _) = Scanner::scan(r#"var x = "a\tb" + 1.5;"#).process_silent();
+    let json = serde_json::to_string(&tokens).unwrap();
+    assert!(json.contains(r#"{"kind":{"Identifier":"x"},"lexeme":"x","span":{"line":1,"column":4,"char_range":{"start":4,"end":5},"bytes_range":{"start":4,"end":5}}}"#));
+
+    // escaped strings can't be borrowed from the JSON, so back they come owned
+    let back: Vec<OwnedToken> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, tokens.iter().map(Token::to_owned).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip_escaped_comment() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let source = "// say \"hi\" \\ bye\nvar x;";
+    let (tokens, _) = Scanner::scan(source).keep_comments(true).process_silent();
+    assert_eq!(tokens[0].kind, TokenKind::LineComment(" say \"hi\" \\ bye"));
+    let json = serde_json::to_string(&tokens).unwrap();
+
+    let back: Vec<OwnedToken> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, tokens.iter().map(Token::to_owned).collect::<Vec<_>>());
+    assert!(serde_json::from_str::<Vec<Token<'_>>>(&json).is_err());
+}
+
+#[test]
+fn render_source_escapes() {
+    let kinds = [
+        TokenKind::Print,
+        TokenKind::String("say \"hi\"\n\\".into()),
+        TokenKind::Number(2.0),
+        TokenKind::LineComment(" done"),
+        TokenKind::Eof,
+    ];
+    assert_eq!(
+        render_source(&kinds),
+        "print \"say \\\"hi\\\"\\n\\\\\" 2.0 // done\n"
+    );
+}
+
+mod round_trip {
+    use loxide::{
+        HandleLoxResultIter, Scanner,
+        token::{OwnedTokenKind, TokenKind, render_source},
+    };
+    use proptest::prelude::*;
+
+    fn unit_kind() -> impl Strategy<Value = OwnedTokenKind> {
+        use OwnedTokenKind::*;
+        prop::sample::select(vec![
+            LeftParen,
+            RightParen,
+            LeftBrace,
+            RightBrace,
+            Colon,
+            Comma,
+            Dot,
+            Minus,
+            Percent,
+            Plus,
+            Question,
+            Semicolon,
+            Slash,
+            Star,
+            Bang,
+            BangEqual,
+            Equal,
+            EqualEqual,
+            FatArrow,
+            Greater,
+            GreaterEqual,
+            Less,
+            LessEqual,
+            PlusEqual,
+            MinusEqual,
+            StarEqual,
+            SlashEqual,
+            And,
+            Break,
+            Class,
+            Continue,
+            Else,
+            False,
+            Fun,
+            For,
+            If,
+            Nil,
+            Or,
+            Print,
+            Return,
+            Super,
+            This,
+            True,
+            Var,
+            While,
+        ])
+    }
+
+    fn kind() -> impl Strategy<Value = OwnedTokenKind> {
+        prop_oneof![
+            unit_kind(),
+            "[a-zA-Z_][a-zA-Z0-9_]{0,8}"
+                .prop_filter("keywords scan as keywords", |id| {
+                    !matches!(
+                        Scanner::scan(id.as_str()).next(),
+                        Some(Ok(token)) if !matches!(token.kind, TokenKind::Identifier(_))
+                    )
+                })
+                .prop_map(OwnedTokenKind::Identifier),
+            any::<String>().prop_map(OwnedTokenKind::String),
+            (0..=i64::MAX).prop_map(OwnedTokenKind::Integer),
+            // no literal for negative numbers or anything not finite
+            (0.0..f64::MAX).prop_map(OwnedTokenKind::Number),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn scan_rendered_tokens(kinds in prop::collection::vec(kind(), 0..24)) {
+            let borrowed = kinds.iter().map(OwnedTokenKind::as_kind).collect::<Vec<_>>();
+            let source = render_source(&borrowed);
+
+            let (tokens, errors) = Scanner::scan(source.as_str()).process_silent();
+            prop_assert_eq!(errors, 0, "{}", source);
+            let scanned = tokens.iter().map(|t| &t.kind).collect::<Vec<_>>();
+            let expected = borrowed.iter().chain([&TokenKind::Eof]).collect::<Vec<_>>();
+            prop_assert_eq!(scanned, expected, "{}", source);
+        }
+    }
+}
+
+#[test]
+fn sort_by_span() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let (tokens, _) = Scanner::scan("var a = \"é\";\nprint a + 1.5;").process_silent();
+    let mut shuffled = tokens.clone();
+    shuffled.reverse();
+    shuffled.swap(1, 7);
+    shuffled.rotate_left(3);
+    assert_ne!(shuffled, tokens);
+
+    shuffled.sort_by(|a, b| a.span.cmp(&b.span));
+    assert_eq!(shuffled, tokens);
+    assert!(tokens.is_sorted());
+
+    assert!(tokens[0] < tokens[1]);
+    let other = t(TokenKind::Identifier("b"), 4, 5);
+    assert_eq!(
+        tokens[1].partial_cmp(&tokens[1].clone()),
+        Some(std::cmp::Ordering::Equal)
+    );
+    assert_eq!(tokens[1].partial_cmp(&other), None);
+}
+
+#[test]
+fn nan_equals_itself() {
+    let nan = t(TokenKind::Number(f64::NAN), 0, 3);
+    assert_eq!(nan, nan.clone());
+    assert_eq!(nan.to_owned(), nan.to_owned());
+    assert_ne!(nan.kind, TokenKind::Number(1.0));
+
+    fn assert_eq_impl<T: Eq>() {}
+    assert_eq_impl::<Token<'_>>();
+    assert_eq_impl::<OwnedToken>();
+    assert_eq!(TokenKind::Number(0.0), TokenKind::Number(-0.0));
+    assert_ne!(TokenKind::Integer(1), TokenKind::Number(1.0));
+    assert_ne!(TokenKind::Identifier("a"), TokenKind::Identifier("b"));
+    assert_eq!(TokenKind::Semicolon, TokenKind::Semicolon);
+    assert_ne!(TokenKind::Semicolon, TokenKind::Comma);
+}
+
+#[test]
+fn classify_kinds() {
+    use loxide::{error::HandleLoxResultIter, scanner::Scanner};
+
+    let (tokens, _) = Scanner::scan("while 123 + (").process_silent();
+    assert!(tokens[0].is_keyword());
+    assert!(tokens[1].is_literal());
+    assert!(tokens[2].is_operator());
+    assert!(tokens[3].is_bracket());
+
+    let (tokens, errors) = Scanner::scan(
+        "( ) { } : , . - % + ? ; / * ! != = == => > >= < <= += -= *= /= \
+         and break class continue else false fun for if nil or print return \
+         super this true var while a \"s\" 1 1.5",
+    )
+    .keep_comments(true)
+    .process_silent();
+    assert_eq!((tokens.len(), errors), (50, 0));
+    let kinds = tokens
+        .iter()
+        .map(|t| t.kind.clone())
+        .chain([TokenKind::LineComment(""), TokenKind::BlockComment("")]);
+    for kind in kinds {
+        let groups = [
+            kind.is_keyword(),
+            kind.is_literal(),
+            kind.is_operator(),
+            kind.is_bracket(),
+        ];
+        let expected = match kind {
+            TokenKind::Comma
+            | TokenKind::Semicolon
+            | TokenKind::Identifier(_)
+            | TokenKind::LineComment(_)
+            | TokenKind::BlockComment(_)
+            | TokenKind::Eof => 0,
+            _ => 1,
+        };
+        assert_eq!(groups.iter().filter(|&&g| g).count(), expected, "{kind:?}");
+    }
+}